@@ -51,6 +51,12 @@ fn main() {
                         .about("which input/output directory to use")
                         .required(true)
                         .index(6),
+                )
+                .arg(
+                    Arg::new("metric")
+                        .about("adaptive: scale metric expression, e.g. \"write_bytes / interval_ms\"")
+                        .long("metric")
+                        .takes_value(true),
                 ),
         )
         .subcommand(
@@ -85,6 +91,12 @@ fn main() {
                         .about("which input/output directory to use")
                         .required(true)
                         .index(5),
+                )
+                .arg(
+                    Arg::new("metric")
+                        .about("adaptive: scale metric expression, e.g. \"write_bytes / interval_ms\"")
+                        .long("metric")
+                        .takes_value(true),
                 ),
         )
         .get_matches();
@@ -25,9 +25,16 @@ pub fn do_single_phase_run(matches: ArgMatches) {
 
     let thread_pool: Arc<dyn Threadpool> = match pool_type {
         "adaptive" => {
-            let adapter_params = ScalingParameters::default();
+            let mut adapter_params = ScalingParameters::default()
+                .try_with_algo_params(pool_params)
+                .expect("invalid pool params");
+            if let Some(metric) = matches.value_of("metric") {
+                adapter_params = adapter_params
+                    .with_metric_expression(metric, None)
+                    .expect("invalid metric expression");
+            }
             AdaptiveThreadpool::new(
-                ScalingAdapter::new(adapter_params.with_algo_params(pool_params))
+                ScalingAdapter::new(adapter_params)
                     .expect("failed to construct adapter parameters"),
             )
         }
@@ -34,12 +34,69 @@ typedef struct {
   CalcMetricsFunFFI calc_interval_metrics;
 } AdapterParameters;
 
+typedef struct {
+  const int32_t *syscall_nrs;
+  uintptr_t amount_syscalls;
+  /**
+   * scale metric as expression, e.g. "syscall(\"fsync\").count * 1000 / interval_ms"
+   */
+  const char *scale_expression;
+  /**
+   * reset metric as expression, NULL to use the scale metric
+   */
+  const char *reset_expression;
+} ExpressionAdapterParameters;
+
+typedef void (*CalcNamedMetricsFunFFI)(const IntervalDataFFI*, double*);
+
+typedef struct {
+  const int32_t *syscall_nrs;
+  uintptr_t amount_syscalls;
+  /**
+   * names of the metrics, calc_named_metrics writes the values in the same order
+   */
+  const char *const *metric_names;
+  uintptr_t amount_metrics;
+  CalcNamedMetricsFunFFI calc_named_metrics;
+  /**
+   * name of the metric that is optimized
+   */
+  const char *scale_metric_name;
+  /**
+   * name of the reset metric, NULL for none
+   */
+  const char *reset_metric_name;
+} NamedAdapterParameters;
+
 bool add_tracee(int32_t tracee_pid);
 
 void close_adapter(void);
 
+/**
+ * pool size to start with as restored from the state file, 0 on a cold start
+ */
+uintptr_t get_initial_size(void);
+
+/**
+ * write the value of the named metric in the latest valid interval to value
+ * returns false if there is no such metric or no valid interval yet, or name or value is NULL
+ */
+bool get_latest_metric(const char *name, double *value);
+
 int32_t get_scaling_advice(int32_t queue_size);
 
+/**
+ * recommended pool size, absolute alternative to get_scaling_advice
+ * returns 0 if there is no recommendation yet or the adapter is busy (keep the current size)
+ */
+uintptr_t get_target_size(int32_t queue_size);
+
+/**
+ * true if the adapter runs without kernel data (traceset unavailable, degraded_fallback
+ * set in the config), it then scales by the reported completed jobs and the queue size
+ */
+bool is_adapter_degraded(void);
+
 /**
  * create new adapter
  * adapter_params: tracked syscalls and metrics calculation function
@@ -51,6 +108,14 @@ int32_t get_scaling_advice(int32_t queue_size);
 bool new_adapter(const AdapterParameters *parameters,
                  const char *algo_params_str);
 
+/**
+ * create new adapter with parameters loaded from a toml/yaml config file
+ * config: path of the config file or name of a shipped preset (e.g. "disk-throughput")
+ *
+ * returns false for a NULL or invalid config
+ */
+bool new_config_adapter(const char *config);
+
 /**
  * create new adapter with default adapter parameters
  * algo_params: comma separated string of all algorithm parameters values (constants that tweak algo)
@@ -60,6 +125,48 @@ bool new_adapter(const AdapterParameters *parameters,
  */
 bool new_default_adapter(const char *algo_params_str);
 
+/**
+ * create new adapter with metrics given as expressions over the interval data
+ * parameters: tracked syscalls and the metric expressions (may only use tracked syscalls)
+ * algo_params: comma separated string of all algorithm parameters values (constants that tweak algo)
+ *
+ * returns false for invalid expressions or an invalid algo parameter string
+ */
+bool new_expression_adapter(const ExpressionAdapterParameters *parameters,
+                            const char *algo_params_str);
+
+/**
+ * create new adapter with default adapter parameters and a metric function of the catalog
+ * metric_name: e.g. "throughput", "syscalls_per_second", see scaling_adapter::metrics::METRICS
+ * algo_params: same as for new_default_adapter
+ *
+ * returns false for an unknown metric name or an invalid algo parameter string
+ */
+bool new_metric_adapter(const char *metric_name, const char *algo_params_str);
+
+/**
+ * create new adapter with named metrics
+ * parameters: tracked syscalls, metric names and the function that calculates their values
+ * algo_params: comma separated string of all algorithm parameters values (constants that tweak algo)
+ *
+ * returns false for unknown metric names, missing names or an invalid check interval
+ */
+bool new_named_adapter(const NamedAdapterParameters *parameters,
+                       const char *algo_params_str);
+
 bool remove_tracee(int32_t tracee_pid);
 
+/**
+ * call after each completed job, used by the job_throughput metric and in degraded mode
+ */
+void report_job_completed(void);
+
+/**
+ * save the learned state (e.g. before close_adapter), restored by adapters with state_file
+ * in their config
+ *
+ * returns false for a NULL or invalid path or if the state file could not be written
+ */
+bool save_adapter_state(const char *path);
+
 #endif /* scaling_adapter_h */
@@ -2,11 +2,15 @@ use std::{ffi::CStr, os::raw::c_char, ptr, sync::RwLock};
 
 use std::io::Write;
 use lazy_static::lazy_static;
-use log::debug;
+use log::{debug, info};
 use scaling_adapter::tracesets::SyscallData;
-use scaling_adapter::{IntervalData, IntervalDerivedData, ScalingAdapter, ScalingParameters};
+use scaling_adapter::{
+    IntervalData, IntervalDerivedData, NamedMetrics, ScalingAdapter, ScalingParameters, PRESETS,
+};
 
 type CalcMetricsFunFFI = unsafe extern "C" fn(&IntervalDataFFI) -> IntervalDerivedData;
+// writes one value per declared metric name into the passed array
+type CalcNamedMetricsFunFFI = unsafe extern "C" fn(&IntervalDataFFI, *mut f64);
 
 // save adapter and the external C function for metrics calculation as globals
 // these will be set when creating a scaling adapter -> maximum one adapter can be created
@@ -14,6 +18,7 @@ lazy_static! {
     static ref ADAPTER: RwLock<Option<ScalingAdapter>> = RwLock::new(None);
     // seems needed, wasn't able to constrain the passed C function to a static lifetime
     static ref CALC_METRICS_FFI: RwLock<Option<CalcMetricsFunFFI>> = RwLock::new(None);
+    static ref CALC_NAMED_METRICS_FFI: RwLock<Option<CalcNamedMetricsFunFFI>> = RwLock::new(None);
 }
 
 #[repr(C)]
@@ -86,6 +91,184 @@ pub extern "C" fn new_adapter(
     (*adapter_global).is_some()
 }
 
+#[repr(C)]
+pub struct NamedAdapterParameters {
+    pub syscall_nrs: *const i32,
+    pub amount_syscalls: usize,
+    /// names of the metrics, calc_named_metrics writes the values in the same order
+    pub metric_names: *const *const c_char,
+    pub amount_metrics: usize,
+    pub calc_named_metrics: CalcNamedMetricsFunFFI,
+    /// name of the metric that is optimized
+    pub scale_metric_name: *const c_char,
+    /// name of the reset metric, NULL for none
+    pub reset_metric_name: *const c_char,
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+/// create new adapter with named metrics
+/// parameters: tracked syscalls, metric names and the function that calculates their values
+/// algo_params: comma separated string of all algorithm parameters values (constants that tweak algo)
+///
+/// returns false for unknown metric names, missing names or an invalid check interval
+pub extern "C" fn new_named_adapter(
+    parameters: &NamedAdapterParameters,
+    algo_params_str: *const c_char,
+) -> bool {
+    init_logging();
+    let mut adapter_global = ADAPTER.write().unwrap();
+    if (parameters.syscall_nrs.is_null() && parameters.amount_syscalls > 0)
+        || parameters.metric_names.is_null()
+        || parameters.amount_metrics == 0
+    {
+        info!("named adapter needs syscall numbers and at least one metric name");
+        return false;
+    }
+    let syscalls_vec: Vec<i32> = if parameters.amount_syscalls == 0 {
+        Vec::new()
+    } else {
+        unsafe {
+            std::slice::from_raw_parts(parameters.syscall_nrs, parameters.amount_syscalls).to_vec()
+        }
+    };
+    let metric_names: Option<Vec<&str>> = unsafe {
+        std::slice::from_raw_parts(parameters.metric_names, parameters.amount_metrics)
+            .iter()
+            .map(|name| c_str(*name, "metric name"))
+            .collect()
+    };
+    let metric_names = match metric_names {
+        Some(metric_names) => metric_names,
+        None => return false,
+    };
+    *CALC_NAMED_METRICS_FFI.write().unwrap() = Some(parameters.calc_named_metrics);
+    let amount_metrics = parameters.amount_metrics;
+    let calc_f = Box::new(move |interval_data: &IntervalData| -> Vec<f64> {
+        let converted = IntervalDataFFI::new(interval_data);
+        let mut values = vec![0.0; amount_metrics];
+        unsafe { CALC_NAMED_METRICS_FFI.read().unwrap().unwrap()(&converted, values.as_mut_ptr()) };
+        values
+    });
+    let scale_metric_name = match c_str(parameters.scale_metric_name, "scale metric name") {
+        Some(name) => name,
+        None => return false,
+    };
+    let reset_metric_name = if parameters.reset_metric_name.is_null() {
+        None
+    } else {
+        match c_str(parameters.reset_metric_name, "reset metric name") {
+            Some(name) => Some(name),
+            None => return false,
+        }
+    };
+    let named_metrics = NamedMetrics::new(&metric_names, calc_f)
+        .try_with_scale_metric(scale_metric_name)
+        .and_then(|named_metrics| match reset_metric_name {
+            Some(name) => named_metrics.try_with_reset_metric(name),
+            None => Ok(named_metrics),
+        });
+    let named_metrics = match named_metrics {
+        Ok(named_metrics) => named_metrics,
+        Err(e) => {
+            info!("invalid named metrics: {}", e);
+            return false;
+        }
+    };
+
+    let check_interval_ms = match c_str(algo_params_str, "parameters string")
+        .and_then(|algo_params| algo_params.split(',').next())
+        .and_then(|check_interval_ms| check_interval_ms.parse::<u64>().ok())
+    {
+        Some(check_interval_ms) => check_interval_ms,
+        None => {
+            info!("could not parse check interval ms");
+            return false;
+        }
+    };
+
+    // metric calculation is replaced by the named metrics
+    let calc_unused = Box::new(|_data: &IntervalData| IntervalDerivedData {
+        scale_metric: 0.0,
+        reset_metric: 0.0,
+    });
+    let params = ScalingParameters::new(syscalls_vec, calc_unused)
+        .with_named_metrics(named_metrics)
+        .with_check_interval_ms(check_interval_ms);
+    *adapter_global = ScalingAdapter::new(params).ok();
+    (*adapter_global).is_some()
+}
+
+#[repr(C)]
+pub struct ExpressionAdapterParameters {
+    pub syscall_nrs: *const i32,
+    pub amount_syscalls: usize,
+    /// scale metric as expression, e.g. "syscall(\"fsync\").count * 1000 / interval_ms"
+    pub scale_expression: *const c_char,
+    /// reset metric as expression, NULL to use the scale metric
+    pub reset_expression: *const c_char,
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+/// create new adapter with metrics given as expressions over the interval data
+/// parameters: tracked syscalls and the metric expressions (may only use tracked syscalls)
+/// algo_params: comma separated string of all algorithm parameters values (constants that tweak algo)
+///
+/// returns false for invalid expressions or an invalid algo parameter string
+pub extern "C" fn new_expression_adapter(
+    parameters: &ExpressionAdapterParameters,
+    algo_params_str: *const c_char,
+) -> bool {
+    init_logging();
+    let mut adapter_global = ADAPTER.write().unwrap();
+    if parameters.syscall_nrs.is_null() && parameters.amount_syscalls > 0 {
+        info!("syscall numbers are NULL");
+        return false;
+    }
+    let syscalls_vec: Vec<i32> = if parameters.amount_syscalls == 0 {
+        Vec::new()
+    } else {
+        unsafe {
+            std::slice::from_raw_parts(parameters.syscall_nrs, parameters.amount_syscalls).to_vec()
+        }
+    };
+    let scale_expression = match c_str(parameters.scale_expression, "scale expression") {
+        Some(scale_expression) => scale_expression,
+        None => return false,
+    };
+    let reset_expression = if parameters.reset_expression.is_null() {
+        None
+    } else {
+        match c_str(parameters.reset_expression, "reset expression") {
+            Some(reset_expression) => Some(reset_expression),
+            None => return false,
+        }
+    };
+    let algo_parameters_str = match c_str(algo_params_str, "parameters string") {
+        Some(algo_parameters_str) => algo_parameters_str,
+        None => return false,
+    };
+
+    let mut params = match ScalingParameters::default().try_with_algo_params(algo_parameters_str) {
+        Ok(params) => params,
+        Err(e) => {
+            info!("invalid algo params {}: {}", algo_parameters_str, e);
+            return false;
+        }
+    };
+    params.syscall_nrs = syscalls_vec;
+    let params = match params.with_metric_expression(scale_expression, reset_expression) {
+        Ok(params) => params,
+        Err(e) => {
+            info!("{}", e);
+            return false;
+        }
+    };
+    *adapter_global = ScalingAdapter::new(params).ok();
+    (*adapter_global).is_some()
+}
+
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 /// create new adapter with default adapter parameters
@@ -111,6 +294,91 @@ pub extern "C" fn new_default_adapter(algo_params_str: *const c_char) -> bool {
     (*adapter_global).is_some()
 }
 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+/// create new adapter with default adapter parameters and a metric function of the catalog
+/// metric_name: e.g. "throughput", "syscalls_per_second", see scaling_adapter::metrics::METRICS
+/// algo_params: same as for new_default_adapter
+///
+/// returns false for an unknown metric name or an invalid algo parameter string
+pub extern "C" fn new_metric_adapter(
+    metric_name: *const c_char,
+    algo_params_str: *const c_char,
+) -> bool {
+    init_logging();
+    let mut adapter_global = ADAPTER.write().unwrap();
+
+    let metric_name = match c_str(metric_name, "metric name") {
+        Some(metric_name) => metric_name,
+        None => return false,
+    };
+    let algo_parameters_str = match c_str(algo_params_str, "parameters string") {
+        Some(algo_parameters_str) => algo_parameters_str,
+        None => return false,
+    };
+    let params = match ScalingParameters::default().try_with_algo_params(algo_parameters_str) {
+        Ok(params) => params,
+        Err(e) => {
+            info!("invalid algo params {}: {}", algo_parameters_str, e);
+            return false;
+        }
+    };
+    let params = match params.with_catalog_metric(metric_name) {
+        Ok(params) => params,
+        Err(e) => {
+            info!("{}", e);
+            return false;
+        }
+    };
+    *adapter_global = ScalingAdapter::new(params).ok();
+    (*adapter_global).is_some()
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+/// create new adapter with parameters loaded from a toml/yaml config file
+/// config: path of the config file or name of a shipped preset (e.g. "disk-throughput")
+///
+/// returns false for a NULL or invalid config
+pub extern "C" fn new_config_adapter(config: *const c_char) -> bool {
+    init_logging();
+    let mut adapter_global = ADAPTER.write().unwrap();
+
+    let config = match c_str(config, "config") {
+        Some(config) => config,
+        None => return false,
+    };
+    let params = if PRESETS.contains(&config) {
+        ScalingParameters::preset(config)
+    } else {
+        ScalingParameters::from_file(config)
+    };
+    let params = match params {
+        Ok(params) => params,
+        Err(e) => {
+            info!("invalid adapter config {}: {}", config, e);
+            return false;
+        }
+    };
+    *adapter_global = ScalingAdapter::new(params).ok();
+    (*adapter_global).is_some()
+}
+
+/// string passed from C, None (logged) for NULL or invalid UTF-8
+fn c_str<'a>(ptr: *const c_char, what: &str) -> Option<&'a str> {
+    if ptr.is_null() {
+        info!("{} is NULL", what);
+        return None;
+    }
+    match unsafe { CStr::from_ptr(ptr) }.to_str() {
+        Ok(string) => Some(string),
+        Err(e) => {
+            info!("invalid {}: {}", what, e);
+            None
+        }
+    }
+}
+
 fn convert_params(
     syscall_nrs: *const i32,
     amount_syscalls: usize,
@@ -167,6 +435,95 @@ pub extern "C" fn get_scaling_advice(queue_size: i32) -> i32 {
     adapter.get_scaling_advice(queue_size)
 }
 
+#[no_mangle]
+/// recommended pool size, absolute alternative to get_scaling_advice
+/// returns 0 if there is no recommendation yet or the adapter is busy (keep the current size)
+pub extern "C" fn get_target_size(queue_size: i32) -> usize {
+    let mut adapter_global = match ADAPTER.try_write() {
+        Ok(option) => option,
+        Err(_) => return 0,
+    };
+    assert!((*adapter_global).is_some());
+    let adapter = adapter_global.as_mut().unwrap();
+    adapter.get_target_size(queue_size).unwrap_or(0)
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+/// write the value of the named metric in the latest valid interval to value
+/// returns false if there is no such metric or no valid interval yet, or name or value is NULL
+pub extern "C" fn get_latest_metric(name: *const c_char, value: *mut f64) -> bool {
+    let adapter_global = ADAPTER.read().unwrap();
+    assert!((*adapter_global).is_some());
+    let adapter = adapter_global.as_ref().unwrap();
+    let name = match c_str(name, "metric name") {
+        Some(name) => name,
+        None => return false,
+    };
+    if value.is_null() {
+        info!("metric value is NULL");
+        return false;
+    }
+    match adapter.get_latest_named_metric(name) {
+        Some(latest) => {
+            unsafe { *value = latest };
+            true
+        }
+        None => false,
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+/// save the learned state (e.g. before close_adapter), restored by adapters with state_file
+/// in their config
+///
+/// returns false for a NULL or invalid path or if the state file could not be written
+pub extern "C" fn save_adapter_state(path: *const c_char) -> bool {
+    let adapter_global = ADAPTER.read().unwrap();
+    assert!((*adapter_global).is_some());
+    let adapter = adapter_global.as_ref().unwrap();
+    let path = match c_str(path, "state path") {
+        Some(path) => path,
+        None => return false,
+    };
+    match adapter.save_state(path) {
+        Ok(()) => true,
+        Err(e) => {
+            info!("could not save adapter state to {}: {}", path, e);
+            false
+        }
+    }
+}
+
+/// pool size to start with as restored from the state file, 0 on a cold start
+#[no_mangle]
+pub extern "C" fn get_initial_size() -> usize {
+    let adapter_global = ADAPTER.read().unwrap();
+    assert!((*adapter_global).is_some());
+    let adapter = adapter_global.as_ref().unwrap();
+    adapter.get_initial_size().unwrap_or(0)
+}
+
+/// true if the adapter runs without kernel data (traceset unavailable, degraded_fallback
+/// set in the config), it then scales by the reported completed jobs and the queue size
+#[no_mangle]
+pub extern "C" fn is_adapter_degraded() -> bool {
+    let adapter_global = ADAPTER.read().unwrap();
+    assert!((*adapter_global).is_some());
+    let adapter = adapter_global.as_ref().unwrap();
+    adapter.is_degraded()
+}
+
+/// call after each completed job, used by the job_throughput metric and in degraded mode
+#[no_mangle]
+pub extern "C" fn report_job_completed() {
+    let adapter_global = ADAPTER.read().unwrap();
+    assert!((*adapter_global).is_some());
+    let adapter = adapter_global.as_ref().unwrap();
+    adapter.job_counter().job_completed();
+}
+
 #[no_mangle]
 pub extern "C" fn close_adapter() {
     let mut adapter_global = ADAPTER.write().unwrap();
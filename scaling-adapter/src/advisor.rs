@@ -0,0 +1,175 @@
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use log::debug;
+
+use crate::ScalingAdapter;
+
+struct Shared {
+    adapter: Mutex<ScalingAdapter>,
+    target_size: AtomicUsize,
+    queue_size: AtomicI32,
+    stopping: Mutex<bool>,
+    stop_signal: Condvar,
+}
+
+/// cheap handle to an advisor, can be cloned and shared between workers
+/// reading the target size never blocks
+#[derive(Clone)]
+pub struct AdvisorHandle {
+    shared: Arc<Shared>,
+}
+
+impl AdvisorHandle {
+    /// recommended pool size, updated after every interval
+    pub fn target_size(&self) -> usize {
+        self.shared.target_size.load(Ordering::Relaxed)
+    }
+
+    /// queue size passed to the adapter at the next interval
+    pub fn report_queue_size(&self, queue_size: i32) {
+        self.shared.queue_size.store(queue_size, Ordering::Relaxed);
+    }
+
+    pub fn add_tracee(&self, tracee_pid: i32) -> bool {
+        self.shared.adapter.lock().unwrap().add_tracee(tracee_pid)
+    }
+
+    pub fn remove_tracee(&self, tracee_pid: i32) -> bool {
+        self.shared
+            .adapter
+            .lock()
+            .unwrap()
+            .remove_tracee(tracee_pid)
+    }
+
+    /// run f with exclusive access to the adapter (e.g. to read its metrics)
+    pub fn with_adapter<T>(&self, f: impl FnOnce(&mut ScalingAdapter) -> T) -> T {
+        f(&mut self.shared.adapter.lock().unwrap())
+    }
+
+    /// signal the advisor thread to stop, returns immediately
+    pub fn stop(&self) {
+        *self.shared.stopping.lock().unwrap() = true;
+        self.shared.stop_signal.notify_all();
+    }
+}
+
+/// runs a scaling adapter on its own thread
+/// a new interval starts every check_interval_ms, independent of the workers,
+/// the recommended pool size is published through the AdvisorHandle
+/// the adapter must use the system clock, the thread sleeps in real time
+pub struct Advisor {
+    handle: AdvisorHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Advisor {
+    /// initial_size: recommended pool size until the adapter gives its first advice
+    pub fn start(adapter: ScalingAdapter, initial_size: usize) -> Self {
+        let shared = Arc::new(Shared {
+            adapter: Mutex::new(adapter),
+            target_size: AtomicUsize::new(initial_size.max(1)),
+            queue_size: AtomicI32::new(0),
+            stopping: Mutex::new(false),
+            stop_signal: Condvar::new(),
+        });
+        let handle = AdvisorHandle { shared };
+        let thread_handle = handle.clone();
+        let thread = thread::Builder::new()
+            .name(String::from("advisor"))
+            .spawn(move || advisor_loop(thread_handle))
+            .expect("thread creation for advisor failed");
+        Advisor {
+            handle,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn handle(&self) -> AdvisorHandle {
+        self.handle.clone()
+    }
+
+    /// stop the advisor thread and wait until it has exited
+    pub fn stop(&mut self) {
+        self.handle.stop();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Advisor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn advisor_loop(handle: AdvisorHandle) {
+    let shared = &handle.shared;
+    loop {
+        let next_interval_due = {
+            let mut adapter = shared.adapter.lock().unwrap();
+            let queue_size = shared.queue_size.load(Ordering::Relaxed);
+            if let Some(target_size) = adapter.get_target_size(queue_size) {
+                let target_size = target_size.max(1);
+                let previous_size = shared.target_size.swap(target_size, Ordering::Relaxed);
+                if target_size != previous_size {
+                    debug!("ADVISOR: target size {}", target_size);
+                }
+            }
+            adapter.next_interval_due()
+        };
+        let mut stopping = shared.stopping.lock().unwrap();
+        loop {
+            if *stopping {
+                return;
+            }
+            let now = Instant::now();
+            if now >= next_interval_due {
+                break;
+            }
+            stopping = shared
+                .stop_signal
+                .wait_timeout(stopping, next_interval_due - now)
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::TestSource;
+    use crate::ScalingParameters;
+    use std::time::Duration;
+
+    #[test]
+    fn advisor_thread() {
+        let source = TestSource::new(1);
+        let params = ScalingParameters::default().with_check_interval_ms(20);
+        let adapter = ScalingAdapter::with_source(params, Box::new(source));
+        let mut advisor = Advisor::start(adapter, 1);
+        let handle = advisor.handle();
+        assert_eq!(handle.target_size(), 1);
+        assert!(handle.add_tracee(1));
+        // startup state always scales up after the first valid interval
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while handle.target_size() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(handle.target_size() >= 2);
+        assert_eq!(
+            handle.with_adapter(|adapter| adapter.get_amount_targets()),
+            1
+        );
+        advisor.stop();
+        // no more updates after the thread has exited
+        let target_size = handle.target_size();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(handle.target_size(), target_size);
+    }
+}
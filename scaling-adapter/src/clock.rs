@@ -0,0 +1,92 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// source of time for the adapter
+/// decisions (interval lengths, timeouts) only use the monotonic time,
+/// the wall clock time is only used to label intervals
+pub trait Clock: Send + Sync {
+    /// monotonic time, never goes backwards
+    fn now(&self) -> Instant;
+    /// wall clock time, may jump (NTP, manual changes)
+    fn wall_time(&self) -> SystemTime;
+}
+
+/// real time of the system
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// clock that only moves when advanced, for tests and simulations
+/// share it with the adapter through an Arc to fast-forward time
+pub struct ManualClock {
+    start: Instant,
+    start_wall_time: SystemTime,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock {
+            start: Instant::now(),
+            start_wall_time: SystemTime::now(),
+            elapsed: Mutex::new(Duration::from_millis(0)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// time advanced since creation
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.start_wall_time + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let start_wall_time = clock.wall_time();
+        assert_eq!(clock.now(), start);
+        clock.advance(Duration::from_secs(5));
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(
+            clock.now().duration_since(start),
+            Duration::from_millis(5500)
+        );
+        assert_eq!(
+            clock.wall_time().duration_since(start_wall_time).unwrap(),
+            Duration::from_millis(5500)
+        );
+        assert_eq!(clock.elapsed(), Duration::from_millis(5500));
+    }
+}
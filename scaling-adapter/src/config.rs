@@ -0,0 +1,337 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::errors::ConfigError;
+use crate::filters::MetricFilter;
+use crate::parameters::{ComparisonMethod, ScalingParameters};
+
+/// names of the presets shipped with the adapter
+pub const PRESETS: [&str; 2] = ["disk-throughput", "fsync-heavy"];
+
+/// syscall numbers on x86_64 of the syscalls that can be selected by name
+const SYSCALLS: [(&str, i32); 24] = [
+    ("read", 0),
+    ("write", 1),
+    ("open", 2),
+    ("close", 3),
+    ("stat", 4),
+    ("fstat", 5),
+    ("lseek", 8),
+    ("mmap", 9),
+    ("pread64", 17),
+    ("pwrite64", 18),
+    ("readv", 19),
+    ("writev", 20),
+    ("fsync", 74),
+    ("fdatasync", 75),
+    ("truncate", 76),
+    ("ftruncate", 77),
+    ("rename", 82),
+    ("mkdir", 83),
+    ("rmdir", 84),
+    ("unlink", 87),
+    ("sync", 162),
+    ("openat", 257),
+    ("unlinkat", 263),
+    ("sync_file_range", 277),
+];
+
+/// number of a syscall given by name (or by number as string)
+pub fn syscall_nr(name: &str) -> Result<i32, ConfigError> {
+    SYSCALLS
+        .iter()
+        .find(|(syscall, _)| *syscall == name)
+        .map(|(_, nr)| *nr)
+        .or_else(|| name.parse().ok())
+        .ok_or_else(|| ConfigError::UnknownSyscall(name.to_string()))
+}
+
+/// scaling parameters as read from a toml or yaml file
+/// all values are optional, missing values are taken from the preset (if given)
+/// or from the default parameters
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParameterConfig {
+    /// name of the preset the other values are applied to
+    pub preset: Option<String>,
+    /// traced syscalls by name, at most 8
+    pub syscalls: Option<Vec<String>>,
+    /// name of a metric function of the catalog, see metrics::METRICS
+    pub scale_metric: Option<String>,
+    /// scale metric as expression over the interval fields, replaces scale_metric
+    /// e.g. "(read_bytes + write_bytes) / interval_ms", see MetricExpression
+    pub scale_expression: Option<String>,
+    /// reset metric as expression, only together with scale_expression
+    pub reset_expression: Option<String>,
+    pub check_interval_ms: Option<u64>,
+    pub stability_factor: Option<f64>,
+    pub comparison: Option<ComparisonMethod>,
+    pub samples_per_size: Option<usize>,
+    pub significance_level: Option<f64>,
+    pub metric_filter: Option<MetricFilter>,
+    pub aging_factor: Option<f64>,
+    pub jump_to_best: Option<bool>,
+    pub min_size: Option<usize>,
+    pub max_size: Option<usize>,
+    pub guard_backoff_ms: Option<u64>,
+    pub settling_intervals: Option<usize>,
+    pub settling_ms: Option<u64>,
+    pub mixed_intervals: Option<bool>,
+    /// learned state of earlier runs, see ScalingParameters::with_state_file
+    pub state_file: Option<String>,
+    pub max_state_age_ms: Option<u64>,
+    /// run without kernel data if the traceset is unavailable
+    pub degraded_fallback: Option<bool>,
+    /// health checks of the traceset counters, see ScalingParameters::with_watchdog
+    pub watchdog: Option<bool>,
+    pub watchdog_max_interval_factor: Option<f64>,
+    pub watchdog_fallback_size: Option<usize>,
+}
+
+impl ParameterConfig {
+    pub fn from_toml_str(config: &str) -> Result<Self, ConfigError> {
+        toml::from_str(config).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    pub fn from_yaml_str(config: &str) -> Result<Self, ConfigError> {
+        serde_yaml::from_str(config).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    /// files ending in .yaml or .yml are read as yaml, everything else as toml
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let config = fs::read_to_string(path).map_err(|e| ConfigError::Io(e.to_string()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => ParameterConfig::from_yaml_str(&config),
+            _ => ParameterConfig::from_toml_str(&config),
+        }
+    }
+
+    pub fn preset(name: &str) -> Result<Self, ConfigError> {
+        let config = match name {
+            "disk-throughput" => include_str!("../presets/disk-throughput.toml"),
+            "fsync-heavy" => include_str!("../presets/fsync-heavy.toml"),
+            _ => return Err(ConfigError::UnknownPreset(name.to_string())),
+        };
+        ParameterConfig::from_toml_str(config)
+    }
+
+    /// default parameters, overwritten by the preset, overwritten by the values of this config
+    pub fn to_parameters(&self) -> Result<ScalingParameters, ConfigError> {
+        let mut params = ScalingParameters::default();
+        let preset = match &self.preset {
+            Some(preset) => Some(ParameterConfig::preset(preset)?),
+            None => None,
+        };
+        if let Some(preset) = &preset {
+            params = preset.apply(params)?;
+        }
+        params = self.apply(params)?;
+        // expressions refer to syscalls by index, compile them against the final syscalls
+        let metric_config = if self.scale_metric.is_some() || self.scale_expression.is_some() {
+            Some(self)
+        } else {
+            preset.as_ref()
+        };
+        if let Some(ParameterConfig {
+            scale_expression: Some(scale_expression),
+            reset_expression,
+            ..
+        }) = metric_config
+        {
+            params =
+                params.with_metric_expression(scale_expression, reset_expression.as_deref())?;
+        }
+        params.validate()?;
+        Ok(params)
+    }
+
+    fn apply(&self, mut params: ScalingParameters) -> Result<ScalingParameters, ConfigError> {
+        if self.scale_metric.is_some() && self.scale_expression.is_some() {
+            return Err(ConfigError::InvalidValue {
+                parameter: "scale_expression",
+                reason: String::from("only one of scale_metric and scale_expression can be set"),
+            });
+        }
+        if self.reset_expression.is_some() && self.scale_expression.is_none() {
+            return Err(ConfigError::InvalidValue {
+                parameter: "reset_expression",
+                reason: String::from("requires scale_expression"),
+            });
+        }
+        if let Some(syscalls) = &self.syscalls {
+            params.syscall_nrs = syscalls
+                .iter()
+                .map(|name| syscall_nr(name))
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(scale_metric) = &self.scale_metric {
+            params = params.with_catalog_metric(scale_metric)?;
+        }
+        if let Some(check_interval_ms) = self.check_interval_ms {
+            params.check_interval_ms = check_interval_ms;
+        }
+        if let Some(stability_factor) = self.stability_factor {
+            params.stability_factor = stability_factor;
+        }
+        if let Some(comparison) = self.comparison {
+            params.comparison = comparison;
+        }
+        if let Some(samples_per_size) = self.samples_per_size {
+            params.samples_per_size = samples_per_size;
+        }
+        if let Some(significance_level) = self.significance_level {
+            params.significance_level = significance_level;
+        }
+        if let Some(metric_filter) = self.metric_filter {
+            params.metric_filter = metric_filter;
+        }
+        if let Some(aging_factor) = self.aging_factor {
+            params.aging_factor = aging_factor;
+        }
+        if let Some(jump_to_best) = self.jump_to_best {
+            params.jump_to_best = jump_to_best;
+        }
+        if let Some(min_size) = self.min_size {
+            params.min_size = min_size;
+        }
+        if let Some(max_size) = self.max_size {
+            params.max_size = max_size;
+        }
+        if let Some(guard_backoff_ms) = self.guard_backoff_ms {
+            params.guard_backoff_ms = guard_backoff_ms;
+        }
+        if let Some(settling_intervals) = self.settling_intervals {
+            params.settling_intervals = settling_intervals;
+        }
+        if let Some(settling_ms) = self.settling_ms {
+            params.settling_ms = settling_ms;
+        }
+        if let Some(mixed_intervals) = self.mixed_intervals {
+            params.mixed_intervals = mixed_intervals;
+        }
+        if let Some(state_file) = &self.state_file {
+            params.state_file = Some(state_file.into());
+        }
+        if let Some(max_state_age_ms) = self.max_state_age_ms {
+            params.max_state_age_ms = max_state_age_ms;
+        }
+        if let Some(degraded_fallback) = self.degraded_fallback {
+            params.degraded_fallback = degraded_fallback;
+        }
+        if let Some(watchdog) = self.watchdog {
+            params.watchdog = watchdog;
+        }
+        if let Some(watchdog_max_interval_factor) = self.watchdog_max_interval_factor {
+            params.watchdog_max_interval_factor = watchdog_max_interval_factor;
+        }
+        if let Some(watchdog_fallback_size) = self.watchdog_fallback_size {
+            params.watchdog_fallback_size = Some(watchdog_fallback_size);
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        for preset in PRESETS.iter() {
+            assert!(ScalingParameters::preset(preset).is_ok());
+        }
+        let params = ParameterConfig::from_toml_str(
+            r#"
+            preset = "fsync-heavy"
+            syscalls = ["write", "fsync"]
+            check_interval_ms = 500
+            settling_intervals = 1
+            comparison = "mannwhitney"
+            metric_filter = { type = "moving_median", window = 3 }
+            "#,
+        )
+        .and_then(|config| config.to_parameters())
+        .unwrap();
+        assert_eq!(params.syscall_nrs, vec![1, 74]);
+        assert_eq!(params.check_interval_ms, 500);
+        assert_eq!(params.settling_intervals, 1);
+        assert_eq!(params.comparison, ComparisonMethod::MannWhitneyU);
+        assert_eq!(
+            params.metric_filter,
+            MetricFilter::MovingMedian { window: 3 }
+        );
+        // from the preset
+        assert_eq!(params.samples_per_size, 3);
+        assert_eq!(params.max_size, 32);
+
+        let params = ParameterConfig::from_yaml_str(
+            "syscalls: [read, write]\nscale_metric: read_throughput\nstability_factor: 0.95\n",
+        )
+        .and_then(|config| config.to_parameters())
+        .unwrap();
+        assert_eq!(params.syscall_nrs, vec![0, 1]);
+        assert!((params.stability_factor - 0.95).abs() < f64::EPSILON);
+
+        let params = ParameterConfig::from_toml_str(
+            r#"
+            preset = "disk-throughput"
+            syscalls = ["write", "fsync"]
+            scale_expression = "syscall(\"fsync\").count * 1000 / interval_ms"
+            "#,
+        )
+        .and_then(|config| config.to_parameters());
+        assert!(params.is_ok());
+
+        let error = |config: &str| {
+            ParameterConfig::from_toml_str(config)
+                .and_then(|config| config.to_parameters())
+                .err()
+                .unwrap()
+        };
+        assert_eq!(
+            error("syscalls = [\"frobnicate\"]"),
+            ConfigError::UnknownSyscall("frobnicate".to_string())
+        );
+        assert_eq!(
+            error("preset = \"cpu-heavy\""),
+            ConfigError::UnknownPreset("cpu-heavy".to_string())
+        );
+        assert!(matches!(
+            error("stability_factor = 1.5"),
+            ConfigError::InvalidValue {
+                parameter: "stability_factor",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("comparison = \"mannwhitney\"\nsamples_per_size = 2"),
+            ConfigError::InvalidValue {
+                parameter: "samples_per_size",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("comparison = \"mannwhitney\"\nsamples_per_size = 3\nsignificance_level = 0.01"),
+            ConfigError::InvalidValue {
+                parameter: "samples_per_size",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("min_size = 8\nmax_size = 4"),
+            ConfigError::InvalidValue { .. }
+        ));
+        assert!(matches!(
+            error("stabilty_factor = 0.9"),
+            ConfigError::Parse(_)
+        ));
+        // not in the default syscalls
+        assert!(matches!(
+            error("scale_expression = 'syscall(\"fdatasync\").count'"),
+            ConfigError::InvalidExpression { .. }
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+use log::{debug, info};
+
+use crate::ScalingAdapter;
+
+/// allocation of one pool of a coordinator
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolAllocation {
+    pub name: String,
+    /// pool size the adapter of the pool recommends
+    pub requested: usize,
+    /// pool size granted by the coordinator, the pool should run with this amount of threads
+    pub allocated: usize,
+}
+
+struct CoordinatedPool {
+    name: String,
+    adapter: ScalingAdapter,
+    requested: usize,
+    allocated: usize,
+}
+
+/// scale-up request of one pool, used to arbitrate the free budget
+struct ScaleUpRequest {
+    pool: usize,
+    threads: usize,
+    /// expected relative metric gain per additional thread
+    gain_per_thread: f64,
+}
+
+/// owns the adapters of several pools that share resources (e.g. the same disk)
+/// the sum of all allocations never exceeds the thread budget (except that every pool gets at
+/// least one thread), scale downs are granted immediately, scale ups in the order of
+/// their expected marginal gain per thread
+pub struct Coordinator {
+    thread_budget: usize,
+    pools: Vec<CoordinatedPool>,
+}
+
+impl Coordinator {
+    pub fn new(thread_budget: usize) -> Self {
+        Coordinator {
+            thread_budget,
+            pools: Vec::new(),
+        }
+    }
+
+    /// returns the id of the pool, pools start with one thread
+    pub fn add_pool(&mut self, name: &str, adapter: ScalingAdapter) -> usize {
+        self.pools.push(CoordinatedPool {
+            name: name.to_string(),
+            adapter,
+            requested: 1,
+            allocated: 1,
+        });
+        self.pools.len() - 1
+    }
+
+    pub fn add_tracee(&mut self, pool: usize, tracee_pid: i32) -> bool {
+        self.pools[pool].adapter.add_tracee(tracee_pid)
+    }
+
+    pub fn remove_tracee(&mut self, pool: usize, tracee_pid: i32) -> bool {
+        self.pools[pool].adapter.remove_tracee(tracee_pid)
+    }
+
+    /// advance the adapter of the pool and return its allocated pool size
+    /// like ScalingAdapter::get_target_size, call it regularly from the pool
+    pub fn get_allocation(&mut self, pool: usize, queue_size: i32) -> usize {
+        let requested = self.pools[pool].adapter.get_target_size(queue_size);
+        if let Some(requested) = requested {
+            if requested != self.pools[pool].requested {
+                debug!(
+                    "COORDINATOR: pool {} requests {} threads",
+                    self.pools[pool].name, requested
+                );
+                self.pools[pool].requested = requested;
+                self.rebalance();
+            }
+        }
+        self.pools[pool].allocated
+    }
+
+    pub fn allocations(&self) -> Vec<PoolAllocation> {
+        self.pools
+            .iter()
+            .map(|pool| PoolAllocation {
+                name: pool.name.clone(),
+                requested: pool.requested,
+                allocated: pool.allocated,
+            })
+            .collect()
+    }
+
+    pub fn thread_budget(&self) -> usize {
+        self.thread_budget
+    }
+
+    /// run f with the adapter of the pool (e.g. to read its metrics)
+    pub fn with_adapter<T>(&mut self, pool: usize, f: impl FnOnce(&mut ScalingAdapter) -> T) -> T {
+        f(&mut self.pools[pool].adapter)
+    }
+
+    fn rebalance(&mut self) {
+        for pool in self.pools.iter_mut() {
+            pool.allocated = pool.allocated.min(pool.requested).max(1);
+        }
+        let used: usize = self.pools.iter().map(|pool| pool.allocated).sum();
+        let mut free = self.thread_budget.saturating_sub(used);
+        let mut requests: Vec<ScaleUpRequest> = self
+            .pools
+            .iter()
+            .enumerate()
+            .filter(|(_, pool)| pool.requested > pool.allocated)
+            .map(|(index, pool)| ScaleUpRequest {
+                pool: index,
+                threads: pool.requested - pool.allocated,
+                gain_per_thread: gain_per_thread(&pool.adapter, pool.allocated, pool.requested),
+            })
+            .collect();
+        requests.sort_by(|a, b| {
+            b.gain_per_thread
+                .partial_cmp(&a.gain_per_thread)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for request in requests {
+            let granted = request.threads.min(free);
+            free -= granted;
+            let pool = &mut self.pools[request.pool];
+            pool.allocated += granted;
+            if granted < request.threads {
+                info!(
+                    "COORDINATOR: pool {} granted {} of {} requested threads, budget exhausted",
+                    pool.name, pool.allocated, pool.requested
+                );
+            }
+        }
+    }
+}
+
+/// relative gain of the scale metric per additional thread, according to the performance table
+/// if the requested size was not observed yet, the metric is assumed to scale linearly
+fn gain_per_thread(adapter: &ScalingAdapter, current: usize, requested: usize) -> f64 {
+    let table = adapter.get_performance_table();
+    let current_mean = match table.get(current) {
+        Some(statistics) if statistics.mean > 0.0 => statistics.mean,
+        _ => return 1.0 / current as f64,
+    };
+    match table.get(requested) {
+        Some(statistics) => {
+            (statistics.mean - current_mean) / current_mean / (requested - current) as f64
+        }
+        None => 1.0 / current as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::TestSource;
+    use crate::{ManualClock, ScalingParameters};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn shared_budget() {
+        let clock = Arc::new(ManualClock::new());
+        let mut coordinator = Coordinator::new(4);
+        let mut next_pid = 0;
+        let pools: Vec<usize> = ["disk-a", "disk-b"]
+            .iter()
+            .map(|name| {
+                let params = ScalingParameters::default().with_clock(clock.clone());
+                let source = TestSource::new(1000);
+                let adapter = ScalingAdapter::with_source(params, Box::new(source));
+                coordinator.add_pool(name, adapter)
+            })
+            .collect();
+        let mut workers: Vec<Vec<i32>> = vec![Vec::new(); pools.len()];
+        let interval = Duration::from_millis(ScalingParameters::default().check_interval_ms);
+        for _ in 0..20 {
+            clock.advance(interval);
+            for &pool in pools.iter() {
+                let allocated = coordinator.get_allocation(pool, 0);
+                // follow the allocation
+                while workers[pool].len() < allocated {
+                    next_pid += 1;
+                    coordinator.add_tracee(pool, next_pid);
+                    workers[pool].push(next_pid);
+                }
+                while workers[pool].len() > allocated {
+                    let pid = workers[pool].pop().unwrap();
+                    coordinator.remove_tracee(pool, pid);
+                }
+            }
+            let used: usize = workers.iter().map(|pool| pool.len()).sum();
+            assert!(used <= coordinator.thread_budget());
+        }
+        // both pools scale linearly, the whole budget is used
+        let allocations = coordinator.allocations();
+        assert_eq!(
+            allocations.iter().map(|pool| pool.allocated).sum::<usize>(),
+            4
+        );
+        for (pool, allocation) in allocations.iter().enumerate() {
+            assert!(allocation.allocated <= allocation.requested.max(1));
+            assert_eq!(workers[pool].len(), allocation.allocated);
+        }
+    }
+}
@@ -1,4 +1,71 @@
+use std::fmt;
+
 #[derive(Debug)]
 pub enum AdapterError {
     TracesetInitFailure,
+    /// parameters failed ScalingParameters::validate
+    InvalidParameters(ConfigError),
 }
+
+/// reason why scaling parameters could not be loaded
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    /// config file could not be read
+    Io(String),
+    /// config is not valid toml/yaml or has unknown keys
+    Parse(String),
+    UnknownPreset(String),
+    UnknownSyscall(String),
+    UnknownMetric(String),
+    /// metric expression could not be parsed or refers to an untraced syscall
+    InvalidExpression {
+        expression: String,
+        reason: String,
+    },
+    /// parameter has a value outside of its allowed range
+    InvalidValue {
+        parameter: &'static str,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "could not parse config: {}", e),
+            ConfigError::UnknownPreset(name) => write!(f, "unknown preset: {}", name),
+            ConfigError::UnknownSyscall(name) => write!(f, "unknown syscall: {}", name),
+            ConfigError::UnknownMetric(name) => write!(f, "unknown metric: {}", name),
+            ConfigError::InvalidExpression { expression, reason } => {
+                write!(f, "invalid expression \"{}\": {}", expression, reason)
+            }
+            ConfigError::InvalidValue { parameter, reason } => {
+                write!(f, "invalid {}: {}", parameter, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// reason why learned state could not be saved or restored
+#[derive(Debug, PartialEq)]
+pub enum StateError {
+    Io(String),
+    Parse(String),
+    /// state is too old or was learned with other parameters
+    Stale(String),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::Io(e) => write!(f, "could not access state file: {}", e),
+            StateError::Parse(e) => write!(f, "invalid state: {}", e),
+            StateError::Stale(reason) => write!(f, "stale state: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
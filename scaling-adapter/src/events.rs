@@ -0,0 +1,263 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use log::{debug, info};
+
+use crate::watchdog::HealthIssue;
+use crate::AdapterState;
+
+/// why the adapter gave its advice
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdviceReason {
+    /// amount of targets changed during the interval, advice 0
+    InvalidInterval,
+    /// targets changed during the interval, kept as mixed interval but not decided on, advice 0
+    MixedInterval,
+    /// not enough samples of the current pool size for a comparison, advice 0
+    CollectingSamples,
+    /// interval discarded while the pool settles after a scaling action, advice 0
+    Settling,
+    /// decision of the built-in state machine
+    StateMachine,
+    /// current pool size underperforms, jump to the best known pool size
+    JumpToBest,
+    /// decision of the configured scaling policy
+    Policy,
+    /// latest interval violated a guard metric, back off
+    GuardViolated,
+    /// scale up rejected, a guard was violated at the target pool size recently
+    GuardLimit,
+    /// advice restricted to the pool size bounds
+    Bounded,
+    /// degraded mode: scale up rejected, no jobs are queued
+    QueueEmpty,
+    /// watchdog rejected the interval, scaling frozen (or pinned to the fallback size)
+    Watchdog,
+}
+
+/// metrics of the interval a decision was based on
+#[derive(Clone, Debug)]
+pub struct IntervalSummary {
+    pub pool_size: usize,
+    pub duration: Duration,
+    pub scale_metric: f64,
+    pub filtered_scale_metric: f64,
+    pub reset_metric: f64,
+    pub guard_values: Vec<(String, f64)>,
+    pub named_values: Vec<(String, f64)>,
+}
+
+/// cumulative counters of the traceset at the time of a decision
+#[derive(Clone, Debug, Default)]
+pub struct TracesetCounters {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub blkio_delay: u64,
+    pub amount_targets: usize,
+}
+
+/// one scaling decision of the adapter
+#[derive(Clone, Debug)]
+pub struct AdviceEvent {
+    pub timestamp: SystemTime,
+    pub previous_state: AdapterState,
+    pub new_state: AdapterState,
+    pub queue_size: i32,
+    pub traceset: TracesetCounters,
+    /// None if the interval was invalid
+    pub interval: Option<IntervalSummary>,
+    pub advice: i32,
+    pub reason: AdviceReason,
+    /// adapter runs without kernel data, see ScalingAdapter::is_degraded
+    pub degraded: bool,
+    /// recommended size the pool did not reach in the latest interval
+    pub unfollowed_target: Option<usize>,
+    /// names of the guards violated in the latest interval
+    pub violated_guards: Vec<String>,
+    /// pool size at which a guard was violated recently, it is not scaled up to
+    pub guard_limit: Option<usize>,
+}
+
+/// interval rejected by the watchdog, raised for every such interval
+#[derive(Clone, Debug)]
+pub struct WatchdogEvent {
+    pub timestamp: SystemTime,
+    pub issue: HealthIssue,
+    pub amount_targets: usize,
+    pub queue_size: i32,
+    /// pool size the pool is pinned to, None if scaling is only frozen
+    pub pinned_size: Option<usize>,
+}
+
+/// receives every advice event of the adapter
+pub trait AdviceObserver: Send + Sync {
+    fn on_advice(&self, event: &AdviceEvent);
+
+    /// ignored by default
+    fn on_watchdog(&self, _event: &WatchdogEvent) {}
+}
+
+impl<F> AdviceObserver for F
+where
+    F: Fn(&AdviceEvent) + Send + Sync,
+{
+    fn on_advice(&self, event: &AdviceEvent) {
+        self(event)
+    }
+}
+
+/// writes the events as the log lines that the evaluation scripts parse
+pub struct LogObserver;
+
+impl AdviceObserver for LogObserver {
+    fn on_advice(&self, event: &AdviceEvent) {
+        info!("ADVICE: new advice, enough time elapsed");
+        info!("_I_QSIZE: {}", event.queue_size);
+        let interval = match &event.interval {
+            Some(interval) => interval,
+            None => {
+                info!("ADVICE: invalid interval (targets changed), advice 0");
+                return;
+            }
+        };
+        if let Some(target_size) = event.unfollowed_target {
+            info!(
+                "ADVICE: not followed, target size {}, actual size {}",
+                target_size, interval.pool_size
+            );
+        }
+        match event.reason {
+            AdviceReason::MixedInterval
+            | AdviceReason::Settling
+            | AdviceReason::Watchdog
+            | AdviceReason::Policy => (),
+            AdviceReason::CollectingSamples => {
+                info!("ADVICE: collecting samples for current pool size, advice 0")
+            }
+            _ => info!("ADVICE: current state: {:?}", event.previous_state),
+        }
+        match event.reason {
+            AdviceReason::JumpToBest => info!(
+                "ADVICE: pool size {} underperforms best known pool size {}",
+                interval.pool_size,
+                interval.pool_size as i32 + event.advice
+            ),
+            AdviceReason::GuardViolated => info!(
+                "ADVICE: guards {:?} violated at pool size {}, back off",
+                event.violated_guards, interval.pool_size
+            ),
+            AdviceReason::GuardLimit => {
+                if let Some(limit) = event.guard_limit {
+                    info!(
+                        "ADVICE: scale up rejected, guard violated at pool size {}",
+                        limit
+                    );
+                }
+            }
+            _ => (),
+        }
+        // the startup decision was never logged with its metrics
+        if event.previous_state == AdapterState::Startup
+            && event.reason == AdviceReason::StateMachine
+        {
+            return;
+        }
+        info!("ADVICE: new state: {:?}", event.new_state);
+        debug!(
+            "ADVICE: last interval ms: {}",
+            interval.duration.as_millis()
+        );
+        info!("_I_PSIZE: {}", interval.pool_size);
+        info!("_I_M1_VAL: {}", interval.scale_metric);
+        info!("_I_M1_FILTERED: {}", interval.filtered_scale_metric);
+        info!("_I_M2_VAL: {}", interval.reset_metric);
+        for (name, value) in interval.guard_values.iter() {
+            info!("_I_GUARD_{}: {}", name.to_uppercase(), value);
+        }
+        for (name, value) in interval.named_values.iter() {
+            info!("_I_METRIC_{}: {}", name.to_uppercase(), value);
+        }
+        debug!("ADVICE: reason: {:?}", event.reason);
+        info!("ADVICE: {}", event.advice);
+    }
+
+    fn on_watchdog(&self, event: &WatchdogEvent) {
+        match event.pinned_size {
+            Some(size) => info!(
+                "WATCHDOG: {:?}, scaling frozen, pool pinned to {}",
+                event.issue, size
+            ),
+            None => info!("WATCHDOG: {:?}, scaling frozen", event.issue),
+        }
+    }
+}
+
+/// sends every event over a channel
+pub struct ChannelObserver {
+    // Sender is not Sync
+    sender: Mutex<Sender<AdviceEvent>>,
+}
+
+impl ChannelObserver {
+    /// observer and the receiving end of its channel
+    pub fn new() -> (Self, Receiver<AdviceEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let observer = ChannelObserver {
+            sender: Mutex::new(sender),
+        };
+        (observer, receiver)
+    }
+}
+
+impl AdviceObserver for ChannelObserver {
+    fn on_advice(&self, event: &AdviceEvent) {
+        // receiver may be gone, events are dropped then
+        let _ = self.sender.lock().unwrap().send(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn observers() {
+        let event = AdviceEvent {
+            timestamp: SystemTime::now(),
+            previous_state: AdapterState::Startup,
+            new_state: AdapterState::Scaling(1),
+            queue_size: 3,
+            traceset: TracesetCounters::default(),
+            interval: None,
+            advice: 1,
+            reason: AdviceReason::StateMachine,
+            degraded: false,
+            unfollowed_target: None,
+            violated_guards: Vec::new(),
+            guard_limit: None,
+        };
+        let (channel_observer, receiver) = ChannelObserver::new();
+        let advice_sum = Arc::new(AtomicI32::new(0));
+        let sum = advice_sum.clone();
+        let closure_observer = move |event: &AdviceEvent| {
+            sum.fetch_add(event.advice, Ordering::SeqCst);
+        };
+        let observers: Vec<Box<dyn AdviceObserver>> = vec![
+            Box::new(LogObserver),
+            Box::new(channel_observer),
+            Box::new(closure_observer),
+        ];
+        for observer in observers.iter() {
+            observer.on_advice(&event);
+            observer.on_advice(&event);
+        }
+        assert_eq!(advice_sum.load(Ordering::SeqCst), 2);
+        let received = receiver.try_recv().unwrap();
+        assert_eq!(received.new_state, AdapterState::Scaling(1));
+        assert_eq!(received.reason, AdviceReason::StateMachine);
+        assert!(receiver.try_recv().is_ok());
+    }
+}
@@ -0,0 +1,408 @@
+use std::fmt;
+
+use crate::config::syscall_nr;
+use crate::errors::ConfigError;
+use crate::intervals::IntervalData;
+
+// grammar:
+// expression := term (("+" | "-") term)*
+// term       := unary (("*" | "/") unary)*
+// unary      := "-" unary | primary
+// primary    := number | field | syscall "." syscall_field | "(" expression ")"
+// syscall    := "syscall" "(" string ")"
+
+/// fields of IntervalData usable in expressions
+const FIELDS: [(&str, Field); 5] = [
+    ("read_bytes", Field::ReadBytes),
+    ("write_bytes", Field::WriteBytes),
+    ("blkio_delay", Field::BlkioDelay),
+    ("interval_ms", Field::IntervalMs),
+    ("amount_targets", Field::AmountTargets),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    ReadBytes,
+    WriteBytes,
+    BlkioDelay,
+    IntervalMs,
+    AmountTargets,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SyscallField {
+    Count,
+    TotalTime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f64),
+    Field(Field),
+    /// index of the syscall in syscall_nrs
+    Syscall(usize, SyscallField),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Text(String),
+    Symbol(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Identifier(identifier) => write!(f, "{}", identifier),
+            Token::Text(text) => write!(f, "\"{}\"", text),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+/// arithmetic expression over the fields of an interval, e.g.
+/// `(read_bytes + write_bytes) / interval_ms` or
+/// `syscall("fsync").total_time / syscall("fsync").count`
+/// division by zero results in 0 (e.g. a syscall without calls in the interval)
+#[derive(Clone, Debug)]
+pub struct MetricExpression {
+    source: String,
+    root: Node,
+}
+
+impl MetricExpression {
+    /// parse the expression, syscalls are resolved by name and must be traced (in syscall_nrs)
+    pub fn compile(source: &str, syscall_nrs: &[i32]) -> Result<Self, ConfigError> {
+        let error = |reason: String| ConfigError::InvalidExpression {
+            expression: source.to_string(),
+            reason,
+        };
+        let tokens = tokenize(source).map_err(error)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            syscall_nrs,
+        };
+        let root = parser.expression().map_err(error)?;
+        if let Some(token) = parser.peek() {
+            return Err(error(format!("unexpected {}", token)));
+        }
+        Ok(MetricExpression {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate(&self, data: &IntervalData) -> f64 {
+        evaluate(&self.root, data)
+    }
+}
+
+fn evaluate(node: &Node, data: &IntervalData) -> f64 {
+    match node {
+        Node::Number(number) => *number,
+        Node::Field(field) => match field {
+            Field::ReadBytes => data.read_bytes as f64,
+            Field::WriteBytes => data.write_bytes as f64,
+            Field::BlkioDelay => data.blkio_delay as f64,
+            Field::IntervalMs => data.duration_millis() as f64,
+            Field::AmountTargets => data.amount_targets as f64,
+        },
+        Node::Syscall(index, field) => match (data.syscalls_data.get(*index), field) {
+            (Some(syscall), SyscallField::Count) => syscall.count as f64,
+            (Some(syscall), SyscallField::TotalTime) => syscall.total_time as f64,
+            (None, _) => 0.0,
+        },
+        Node::Negate(operand) => -evaluate(operand, data),
+        Node::Binary(operator, left, right) => {
+            let left = evaluate(left, data);
+            let right = evaluate(right, data);
+            match operator {
+                Operator::Add => left + right,
+                Operator::Subtract => left - right,
+                Operator::Multiply => left * right,
+                Operator::Divide if right == 0.0 => 0.0,
+                Operator::Divide => left / right,
+            }
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            let number = number
+                .parse()
+                .map_err(|_| format!("invalid number {}", number))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut identifier = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                identifier.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Identifier(identifier));
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => text.push(c),
+                    None => return Err(String::from("unterminated string")),
+                }
+            }
+            tokens.push(Token::Text(text));
+        } else if "+-*/().".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("unexpected character {}", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    syscall_nrs: &'a [i32],
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| String::from("unexpected end of expression"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        match self.next()? {
+            Token::Symbol(c) if c == symbol => Ok(()),
+            token => Err(format!("expected {}, found {}", symbol, token)),
+        }
+    }
+
+    /// next token if it is one of the symbols
+    fn symbol_of(&mut self, symbols: &str) -> Option<char> {
+        match self.peek() {
+            Some(Token::Symbol(c)) if symbols.contains(*c) => {
+                let c = *c;
+                self.position += 1;
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        while let Some(symbol) = self.symbol_of("+-") {
+            let operator = if symbol == '+' {
+                Operator::Add
+            } else {
+                Operator::Subtract
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while let Some(symbol) = self.symbol_of("*/") {
+            let operator = if symbol == '*' {
+                Operator::Multiply
+            } else {
+                Operator::Divide
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.symbol_of("-").is_some() {
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.next()? {
+            Token::Number(number) => Ok(Node::Number(number)),
+            Token::Symbol('(') => {
+                let node = self.expression()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Token::Identifier(identifier) if identifier == "syscall" => self.syscall(),
+            Token::Identifier(identifier) => FIELDS
+                .iter()
+                .find(|(name, _)| *name == identifier)
+                .map(|(_, field)| Node::Field(*field))
+                .ok_or_else(|| format!("unknown field {}", identifier)),
+            token => Err(format!("unexpected {}", token)),
+        }
+    }
+
+    fn syscall(&mut self) -> Result<Node, String> {
+        self.expect('(')?;
+        let name = match self.next()? {
+            Token::Text(name) => name,
+            token => return Err(format!("expected syscall name, found {}", token)),
+        };
+        self.expect(')')?;
+        let nr = syscall_nr(&name).map_err(|e| e.to_string())?;
+        let index = self
+            .syscall_nrs
+            .iter()
+            .position(|traced| *traced == nr)
+            .ok_or_else(|| format!("syscall {} is not traced", name))?;
+        self.expect('.')?;
+        let field = match self.next()? {
+            Token::Identifier(field) if field == "count" => SyscallField::Count,
+            Token::Identifier(field) if field == "total_time" => SyscallField::TotalTime,
+            token => return Err(format!("expected count or total_time, found {}", token)),
+        };
+        Ok(Node::Syscall(index, field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+    use std::time::{Duration, SystemTime};
+    use tracesets::{SyscallData, TracesetSnapshot};
+
+    #[test]
+    fn expressions() {
+        let syscalls_data = vec![
+            SyscallData {
+                count: 10,
+                total_time: 300,
+            },
+            SyscallData {
+                count: 0,
+                total_time: 0,
+            },
+        ];
+        let data = IntervalData {
+            read_bytes: 1000,
+            write_bytes: 4000,
+            blkio_delay: 50,
+            ..IntervalData::for_test(Duration::from_millis(500), 4, syscalls_data)
+        };
+        // write, fsync
+        let syscall_nrs = [1, 74];
+        let value = |source: &str| {
+            MetricExpression::compile(source, &syscall_nrs)
+                .unwrap()
+                .evaluate(&data)
+        };
+        assert_eq!(value("(read_bytes + write_bytes) / interval_ms"), 10.0);
+        assert_eq!(value("read_bytes + write_bytes / interval_ms"), 1008.0);
+        assert_eq!(value("-blkio_delay * 2 - -1"), -99.0);
+        assert_eq!(value("write_bytes / amount_targets / 0.5"), 2000.0);
+        assert_eq!(
+            value("syscall(\"write\").total_time / syscall(\"write\").count"),
+            30.0
+        );
+        assert_eq!(
+            value("syscall(\"fsync\").total_time / syscall(\"fsync\").count"),
+            0.0
+        );
+        let reason = |source: &str| match MetricExpression::compile(source, &syscall_nrs) {
+            Err(ConfigError::InvalidExpression { reason, .. }) => reason,
+            _ => panic!("expected invalid expression"),
+        };
+        assert_eq!(reason("read_bytes +"), "unexpected end of expression");
+        assert_eq!(reason("read_byte"), "unknown field read_byte");
+        assert_eq!(reason("(read_bytes"), "unexpected end of expression");
+        assert_eq!(reason("read_bytes write_bytes"), "unexpected write_bytes");
+        assert_eq!(
+            reason("syscall(\"read\").count"),
+            "syscall read is not traced"
+        );
+        assert_eq!(
+            reason("syscall(\"write\").latency"),
+            "expected count or total_time, found latency"
+        );
+        assert_eq!(reason("read_bytes % 2"), "unexpected character %");
+    }
+
+    #[test]
+    fn syscalls_of_snapshots() {
+        // read, write, fsync, openat, unlink
+        let syscall_nrs = [0, 1, 74, 257, 87];
+        let snapshot = |factor: u32| TracesetSnapshot {
+            read_bytes: 0,
+            write_bytes: 0,
+            blkio_delay: 0,
+            syscalls_data: syscall_nrs
+                .iter()
+                .map(|nr| {
+                    let count = *nr as u32 * factor;
+                    let total_time = 10 * count as u64;
+                    (*nr, SyscallData { count, total_time })
+                })
+                .collect::<HashMap<i32, SyscallData>>(),
+            targets: HashSet::new(),
+            timestamp: SystemTime::now(),
+        };
+        let duration = Duration::from_millis(1000);
+        let data = IntervalData::new(&snapshot(1), &snapshot(2), duration, &syscall_nrs).unwrap();
+        let names = ["read", "write", "fsync", "openat", "unlink"];
+        for (name, nr) in names.iter().zip(syscall_nrs.iter()) {
+            let count = format!("syscall(\"{}\").count", name);
+            let total_time = format!("syscall(\"{}\").total_time", name);
+            let value = |source: &str| {
+                MetricExpression::compile(source, &syscall_nrs)
+                    .unwrap()
+                    .evaluate(&data)
+            };
+            assert_eq!(value(&count), *nr as f64);
+            assert_eq!(value(&total_time), 10.0 * *nr as f64);
+        }
+    }
+}
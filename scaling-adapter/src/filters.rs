@@ -0,0 +1,112 @@
+use serde::Deserialize;
+
+use crate::intervals::IntervalMetrics;
+
+/// smoothing applied to the scale metric before it is used for scaling decisions
+/// the filter only considers preceding intervals with the same amount of targets,
+/// so a change of the pool size is never smoothed away
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MetricFilter {
+    /// use the raw scale metric
+    None,
+    /// exponentially weighted moving average, 0 < alpha <= 1 (weight of the newest value)
+    Ewma { alpha: f64 },
+    /// median of the raw values of the last window intervals (including the newest)
+    MovingMedian { window: usize },
+    /// replace the newest value with the median of the last window intervals if it deviates
+    /// more than threshold scaled median absolute deviations from that median
+    OutlierRejection { window: usize, threshold: f64 },
+}
+
+impl MetricFilter {
+    /// filter the raw scale metric of the newest interval
+    /// previous: preceding intervals with the same amount of targets, from newest to oldest
+    pub fn apply(&self, raw: f64, previous: &[&IntervalMetrics]) -> f64 {
+        match *self {
+            MetricFilter::None => raw,
+            MetricFilter::Ewma { alpha } => match previous.first() {
+                Some(last) => alpha * raw + (1.0 - alpha) * last.filtered_scale_metric,
+                None => raw,
+            },
+            MetricFilter::MovingMedian { window } => {
+                let mut values = window_values(raw, previous, window);
+                median(&mut values)
+            }
+            MetricFilter::OutlierRejection { window, threshold } => {
+                let mut values = window_values(raw, previous, window);
+                // too few values to tell what an outlier is
+                if values.len() < 3 {
+                    return raw;
+                }
+                let center = median(&mut values);
+                let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+                // scale MAD to be consistent with the standard deviation of normal data
+                let mad = 1.4826 * median(&mut deviations);
+                if mad > 0.0 && (raw - center).abs() > threshold * mad {
+                    center
+                } else {
+                    raw
+                }
+            }
+        }
+    }
+}
+
+/// newest raw value followed by the raw values of the previous intervals, at most window values
+fn window_values(raw: f64, previous: &[&IntervalMetrics], window: usize) -> Vec<f64> {
+    let mut values = vec![raw];
+    values.extend(
+        previous
+            .iter()
+            .take(window.saturating_sub(1))
+            .map(|metrics| metrics.derived_data.scale_metric),
+    );
+    values
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let middle = values.len() / 2;
+    // odd amount of values has a single middle element
+    if values.len() > 2 * middle {
+        values[middle]
+    } else {
+        (values[middle - 1] + values[middle]) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(raw: f64, filtered: f64) -> IntervalMetrics {
+        IntervalMetrics {
+            filtered_scale_metric: filtered,
+            ..IntervalMetrics::for_test(1, raw)
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let history = [
+            metrics(10.0, 10.0),
+            metrics(12.0, 11.0),
+            metrics(11.0, 11.0),
+        ];
+        let previous: Vec<&IntervalMetrics> = history.iter().collect();
+        assert_eq!(MetricFilter::None.apply(50.0, &previous), 50.0);
+        let ewma = MetricFilter::Ewma { alpha: 0.5 };
+        assert_eq!(ewma.apply(20.0, &previous), 15.0);
+        assert_eq!(ewma.apply(20.0, &[]), 20.0);
+        let median = MetricFilter::MovingMedian { window: 3 };
+        assert_eq!(median.apply(50.0, &previous), 12.0);
+        let outliers = MetricFilter::OutlierRejection {
+            window: 4,
+            threshold: 3.0,
+        };
+        // spike is replaced by median of window, regular value is kept
+        assert_eq!(outliers.apply(50.0, &previous), 11.5);
+        assert_eq!(outliers.apply(11.5, &previous), 11.5);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::errors::ConfigError;
+use crate::intervals::IntervalData;
+
+/// calculates the value of a guard metric from the interval data
+pub type GuardCalc = Box<dyn Fn(&IntervalData) -> f64 + Send + Sync>;
+
+/// metric that must stay below a ceiling, independent of the scale metric
+/// if the latest interval violates a guard the adapter backs off and does not grow the pool
+/// to that size again until the guard backoff passed
+pub struct GuardMetric {
+    pub name: String,
+    /// None: the value is the named metric with the same name
+    pub calc: Option<GuardCalc>,
+    /// guard is violated if the value is above the ceiling
+    pub ceiling: f64,
+}
+
+impl GuardMetric {
+    pub fn new(name: &str, ceiling: f64, calc: GuardCalc) -> Self {
+        GuardMetric {
+            name: name.to_string(),
+            calc: Some(calc),
+            ceiling,
+        }
+    }
+
+    /// guard on the named metric with the given name
+    /// the name must be declared by the named metrics, see ScalingParameters::validate
+    pub fn named(name: &str, ceiling: f64) -> Self {
+        GuardMetric {
+            name: name.to_string(),
+            calc: None,
+            ceiling,
+        }
+    }
+
+    /// value of the guard for the interval, named_value: value of the named metric with the same name
+    pub fn value(&self, data: &IntervalData, named_value: Option<f64>) -> f64 {
+        match &self.calc {
+            Some(calc) => calc(data),
+            None => named_value.unwrap_or(0.0),
+        }
+    }
+
+    /// blkio delay of all targets per second of the interval
+    pub fn blkio_delay_per_second(ceiling: f64) -> Self {
+        GuardMetric::new(
+            "blkio_delay_per_second",
+            ceiling,
+            Box::new(|data: &IntervalData| {
+                data.blkio_delay as f64 * 1000.0 / data.duration_millis().max(1) as f64
+            }),
+        )
+    }
+
+    /// average time per call of the syscall syscall_nr, which must be one of the traced syscall_nrs
+    /// intervals without any call have an average latency of 0
+    pub fn average_syscall_latency(
+        syscall_nr: i32,
+        syscall_nrs: &[i32],
+        ceiling: f64,
+    ) -> Result<Self, ConfigError> {
+        let syscall_index = syscall_nrs
+            .iter()
+            .position(|nr| *nr == syscall_nr)
+            .ok_or_else(|| ConfigError::InvalidValue {
+                parameter: "average_syscall_latency",
+                reason: format!("syscall {} is not traced", syscall_nr),
+            })?;
+        Ok(GuardMetric::new(
+            "average_syscall_latency",
+            ceiling,
+            Box::new(
+                move |data: &IntervalData| match data.syscalls_data.get(syscall_index) {
+                    Some(syscall) if syscall.count > 0 => {
+                        syscall.total_time as f64 / syscall.count as f64
+                    }
+                    _ => 0.0,
+                },
+            ),
+        ))
+    }
+
+    pub fn is_violated(&self, value: f64) -> bool {
+        value > self.ceiling
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::named_metrics::NamedMetrics;
+    use crate::parameters::ScalingParameters;
+    use std::collections::{HashMap, HashSet};
+    use std::time::{Duration, SystemTime};
+    use tracesets::{SyscallData, TracesetSnapshot};
+
+    #[test]
+    fn guard_metrics() {
+        let syscalls_data = vec![
+            SyscallData {
+                count: 4,
+                total_time: 100,
+            },
+            SyscallData {
+                count: 0,
+                total_time: 0,
+            },
+        ];
+        let data = IntervalData {
+            blkio_delay: 400,
+            ..IntervalData::for_test(Duration::from_millis(500), 2, syscalls_data)
+        };
+        let blkio = GuardMetric::blkio_delay_per_second(700.0);
+        assert_eq!(blkio.value(&data, None), 800.0);
+        assert!(blkio.is_violated(800.0));
+        // write, fsync
+        let syscall_nrs = [1, 74];
+        let latency = GuardMetric::average_syscall_latency(1, &syscall_nrs, 30.0).unwrap();
+        assert_eq!(latency.value(&data, None), 25.0);
+        assert!(!latency.is_violated(25.0));
+        let no_calls = GuardMetric::average_syscall_latency(74, &syscall_nrs, 30.0).unwrap();
+        assert_eq!(no_calls.value(&data, None), 0.0);
+        assert!(GuardMetric::average_syscall_latency(0, &syscall_nrs, 30.0).is_err());
+        let named = GuardMetric::named("queue_length", 10.0);
+        assert_eq!(named.value(&data, Some(12.0)), 12.0);
+        assert!(named.is_violated(12.0));
+    }
+
+    #[test]
+    fn unknown_named_guard() {
+        let named_metrics = || NamedMetrics::new(&["queue_length"], Box::new(|_| vec![0.0]));
+        let params = ScalingParameters::default()
+            .with_named_metrics(named_metrics())
+            .with_guard(GuardMetric::named("queue_length", 10.0));
+        assert!(params.validate().is_ok());
+        let params = ScalingParameters::default()
+            .with_named_metrics(named_metrics())
+            .with_guard(GuardMetric::named("latency", 10.0));
+        assert_eq!(
+            params.validate().err(),
+            Some(ConfigError::UnknownMetric(String::from("latency")))
+        );
+        let params = ScalingParameters::default().with_guard(GuardMetric::named("latency", 10.0));
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn latency_of_snapshots() {
+        // read, write, fsync, openat, unlink
+        let syscall_nrs = [0, 1, 74, 257, 87];
+        let snapshot = |factor: u64| TracesetSnapshot {
+            read_bytes: 0,
+            write_bytes: 0,
+            blkio_delay: 0,
+            syscalls_data: syscall_nrs
+                .iter()
+                .map(|nr| {
+                    let data = SyscallData {
+                        count: 2 * factor as u32,
+                        total_time: *nr as u64 * factor,
+                    };
+                    (*nr, data)
+                })
+                .collect::<HashMap<i32, SyscallData>>(),
+            targets: HashSet::new(),
+            timestamp: SystemTime::now(),
+        };
+        let duration = Duration::from_millis(1000);
+        let data = IntervalData::new(&snapshot(1), &snapshot(2), duration, &syscall_nrs).unwrap();
+        for nr in syscall_nrs.iter() {
+            let latency = GuardMetric::average_syscall_latency(*nr, &syscall_nrs, 30.0).unwrap();
+            assert_eq!(latency.value(&data, None), *nr as f64 / 2.0);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use std::time::{SystemTime};
+use std::time::{Duration, SystemTime};
 
 use log::{debug, info};
 use tracesets::{SyscallData, TracesetSnapshot};
@@ -11,12 +11,21 @@ use tracesets::{SyscallData, TracesetSnapshot};
 pub struct IntervalData {
     pub start: SystemTime,
     pub end: SystemTime,
+    /// length of the interval measured with the monotonic clock
+    pub duration: Duration,
     pub read_bytes: u64,
     pub write_bytes: u64,
     pub blkio_delay: u64,
     // same order as syscall_nr vec passed in ScalingParameters
     pub syscalls_data: Vec<SyscallData>,
+    /// for mixed intervals the time weighted average amount of targets, rounded
     pub amount_targets: usize,
+    /// sum of the time each target was registered during the interval (thread-time)
+    pub target_time: Duration,
+    /// targets changed during the interval
+    pub mixed: bool,
+    /// jobs the pool reported as completed during the interval, see ScalingAdapter::job_counter
+    pub completed_jobs: u64,
 }
 
 // as IntervalData is read-only this should be safe
@@ -34,9 +43,12 @@ fn subtract_or_zero(a: u64, b: u64, context: &str) -> u64 {
 }
 
 impl IntervalData {
+    /// syscall_nrs: traced syscalls, syscalls_data is built in this order
     pub fn new(
         snapshot_earlier: &TracesetSnapshot,
         snapshot_later: &TracesetSnapshot,
+        duration: Duration,
+        syscall_nrs: &[i32],
     ) -> Option<IntervalData> {
         debug!(
             "create interval data, earlier snapshot targets: {:?}, new snapshot targets: {:?}",
@@ -44,52 +56,119 @@ impl IntervalData {
         );
         let targets_match = snapshot_earlier.targets.eq(&snapshot_later.targets);
         if targets_match {
-            let read_bytes = subtract_or_zero(snapshot_later.read_bytes, snapshot_earlier.read_bytes, "rb");
-            let write_bytes = subtract_or_zero(snapshot_later.write_bytes, snapshot_earlier.write_bytes, "wb");
-            let blkio_delay = subtract_or_zero(snapshot_later.blkio_delay, snapshot_earlier.blkio_delay, "blkio");
             let amount_targets = snapshot_earlier.targets.len();
-            let mut syscalls_data = Vec::new();
-            for syscall in snapshot_earlier.syscalls_data.keys() {
-                let earlier_data = snapshot_earlier.syscalls_data.get(syscall).unwrap();
-                let later_data = snapshot_later.syscalls_data.get(syscall).unwrap();
-                let count_diff = later_data.count - earlier_data.count;
-                let time_diff = later_data.total_time - earlier_data.total_time;
-                let syscall_data_diff = SyscallData {
-                    count: count_diff,
-                    total_time: time_diff,
-                };
-                syscalls_data.push(syscall_data_diff);
-            }
-            let start = snapshot_earlier.timestamp;
-            let end = snapshot_later.timestamp;
-            Some(IntervalData {
-                start,
-                end,
-                read_bytes,
-                write_bytes,
-                blkio_delay,
-                syscalls_data,
-                amount_targets,
-            })
+            let target_time = duration * amount_targets as u32;
+            Some(IntervalData::from_snapshots(
+                snapshot_earlier,
+                snapshot_later,
+                duration,
+                target_time,
+                syscall_nrs,
+            ))
         } else {
             None
         }
     }
 
+    /// unlike new, also creates an interval if targets changed during the interval
+    /// target_time: sum of the time each target was registered during the interval
+    pub fn new_mixed(
+        snapshot_earlier: &TracesetSnapshot,
+        snapshot_later: &TracesetSnapshot,
+        duration: Duration,
+        target_time: Duration,
+        syscall_nrs: &[i32],
+    ) -> IntervalData {
+        IntervalData::from_snapshots(
+            snapshot_earlier,
+            snapshot_later,
+            duration,
+            target_time,
+            syscall_nrs,
+        )
+    }
+
+    fn from_snapshots(
+        snapshot_earlier: &TracesetSnapshot,
+        snapshot_later: &TracesetSnapshot,
+        duration: Duration,
+        target_time: Duration,
+        syscall_nrs: &[i32],
+    ) -> IntervalData {
+        let constant_amount_targets = snapshot_earlier.targets.len();
+        let mixed = snapshot_earlier.targets != snapshot_later.targets
+            || target_time != duration * constant_amount_targets as u32;
+        let amount_targets = if !mixed {
+            constant_amount_targets
+        } else if duration.as_nanos() == 0 {
+            snapshot_later.targets.len()
+        } else {
+            (target_time.as_secs_f64() / duration.as_secs_f64()).round() as usize
+        };
+        let read_bytes = subtract_or_zero(snapshot_later.read_bytes, snapshot_earlier.read_bytes, "rb");
+        let write_bytes = subtract_or_zero(snapshot_later.write_bytes, snapshot_earlier.write_bytes, "wb");
+        let blkio_delay = subtract_or_zero(snapshot_later.blkio_delay, snapshot_earlier.blkio_delay, "blkio");
+        // snapshots hold the syscalls in a map, the metrics refer to them by position
+        let mut syscalls_data = Vec::with_capacity(syscall_nrs.len());
+        for syscall in syscall_nrs {
+            let syscall_data_diff = match (
+                snapshot_earlier.syscalls_data.get(syscall),
+                snapshot_later.syscalls_data.get(syscall),
+            ) {
+                // counters of targets leaving during a mixed interval may be lost
+                (Some(earlier_data), Some(later_data)) => SyscallData {
+                    count: later_data.count.saturating_sub(earlier_data.count),
+                    total_time: later_data
+                        .total_time
+                        .saturating_sub(earlier_data.total_time),
+                },
+                // sources without kernel data do not count syscalls
+                _ => SyscallData {
+                    count: 0,
+                    total_time: 0,
+                },
+            };
+            syscalls_data.push(syscall_data_diff);
+        }
+        let start = snapshot_earlier.timestamp;
+        let end = snapshot_later.timestamp;
+        IntervalData {
+            start,
+            end,
+            duration,
+            read_bytes,
+            write_bytes,
+            blkio_delay,
+            syscalls_data,
+            amount_targets,
+            target_time,
+            mixed,
+            completed_jobs: 0,
+        }
+    }
+
     // can safely use as_millis as u64 (only overflow at unix epoch + half billion years)
+    // wall clock time before the unix epoch results in 0
     pub fn start_millis(&self) -> u64 {
         self.start
             .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("interval start before unix epoch")
-            .as_millis() as u64
+            .map(|since_epoch| since_epoch.as_millis() as u64)
+            .unwrap_or(0)
     }
 
     // can safely use as_millis as u64 (only overflow at unix epoch + half billion years)
+    // wall clock time before the unix epoch results in 0
     pub fn end_millis(&self) -> u64 {
         self.end
             .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("interval start before unix epoch")
-            .as_millis() as u64
+            .map(|since_epoch| since_epoch.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    /// length of the interval in ms (monotonic), unlike end_millis - start_millis
+    /// it is not affected by changes of the wall clock
+    pub fn duration_millis(&self) -> u64 {
+        self.duration.as_millis() as u64
     }
 }
 
@@ -110,25 +189,90 @@ pub struct IntervalDerivedData {
 
 pub struct IntervalMetrics {
     pub derived_data: IntervalDerivedData,
+    /// scale metric after smoothing, used for scaling decisions
+    pub filtered_scale_metric: f64,
+    /// values of the guard metrics, same order as the guards in ScalingParameters
+    pub guard_values: Vec<f64>,
+    /// values of the named metrics, same order as the names in ScalingParameters
+    pub named_values: Vec<f64>,
     pub amount_targets: usize,
     pub interval_start: SystemTime,
     pub interval_end: SystemTime,
+    /// length of the interval measured with the monotonic clock
+    pub interval_duration: Duration,
+    /// targets changed during the interval, see IntervalData::new_mixed
+    pub mixed: bool,
+    /// share of the interval during which the pool had amount_targets targets (1 if not mixed)
+    pub weight: f64,
 }
 
 impl IntervalMetrics {
      // can safely use as_millis as u64 (only overflow at unix epoch + half billion years)
+     // wall clock time before the unix epoch results in 0
      pub fn start_millis(&self) -> u64 {
         self.interval_start
             .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("interval start before unix epoch")
-            .as_millis() as u64
+            .map(|since_epoch| since_epoch.as_millis() as u64)
+            .unwrap_or(0)
     }
 
     // can safely use as_millis as u64 (only overflow at unix epoch + half billion years)
+    // wall clock time before the unix epoch results in 0
     pub fn end_millis(&self) -> u64 {
         self.interval_end
             .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("interval start before unix epoch")
-            .as_millis() as u64
+            .map(|since_epoch| since_epoch.as_millis() as u64)
+            .unwrap_or(0)
     }
-}
\ No newline at end of file
+
+    /// length of the interval in ms (monotonic)
+    pub fn duration_millis(&self) -> u64 {
+        self.interval_duration.as_millis() as u64
+    }
+}
+#[cfg(test)]
+impl IntervalData {
+    /// interval with the given syscall data that ends now, other counters are 0, for tests
+    pub(crate) fn for_test(
+        duration: Duration,
+        amount_targets: usize,
+        syscalls_data: Vec<SyscallData>,
+    ) -> Self {
+        let start = SystemTime::now();
+        IntervalData {
+            start,
+            end: start + duration,
+            duration,
+            read_bytes: 0,
+            write_bytes: 0,
+            blkio_delay: 0,
+            syscalls_data,
+            amount_targets,
+            target_time: duration * amount_targets as u32,
+            mixed: false,
+            completed_jobs: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+impl IntervalMetrics {
+    /// one second interval with the given scale metric (raw and filtered), for tests
+    pub(crate) fn for_test(amount_targets: usize, scale_metric: f64) -> Self {
+        IntervalMetrics {
+            derived_data: IntervalDerivedData {
+                scale_metric,
+                reset_metric: 0.0,
+            },
+            filtered_scale_metric: scale_metric,
+            guard_values: Vec::new(),
+            named_values: Vec::new(),
+            amount_targets,
+            interval_start: SystemTime::now(),
+            interval_end: SystemTime::now(),
+            interval_duration: Duration::from_millis(1000),
+            mixed: false,
+            weight: 1.0,
+        }
+    }
+}
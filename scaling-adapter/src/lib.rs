@@ -1,79 +1,395 @@
 #![allow(dead_code)]
-use std::time::{Duration, SystemTime};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use errors::AdapterError;
-use intervals::IntervalMetrics;
 use log::{debug, info};
 use tracesets::{Traceset, TracesetSnapshot};
 use AdapterState::Settled;
 
 // need to make import public for it to be visible in dependant library/exe
 // https://stackoverflow.com/questions/62933825/why-we-need-to-specify-all-dependenciesincluding-transitives-in-rust
-pub use intervals::{IntervalData, IntervalDerivedData};
-pub use parameters::ScalingParameters;
+pub use advisor::{Advisor, AdvisorHandle};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use config::{syscall_nr, ParameterConfig, PRESETS};
+pub use coordinator::{Coordinator, PoolAllocation};
+pub use errors::{AdapterError, ConfigError, StateError};
+pub use events::{
+    AdviceEvent, AdviceObserver, AdviceReason, ChannelObserver, IntervalSummary, LogObserver,
+    TracesetCounters, WatchdogEvent,
+};
+pub use expression::MetricExpression;
+pub use filters::MetricFilter;
+pub use guards::{GuardCalc, GuardMetric};
+pub use intervals::{IntervalData, IntervalDerivedData, IntervalMetrics};
+pub use named_metrics::{NamedMetrics, NamedMetricsCalc};
+pub use parameters::{ComparisonMethod, ScalingParameters};
+pub use performance::{PerformanceTable, SizeStatistics};
+pub use policies::{
+    ArmEstimate, BanditAlgorithm, BanditPolicy, GoldenSectionPolicy, MetricResponse, PidGains,
+    PidPolicy, PolicyContext, ScalingPolicy, TrackedMetric, UslModel, UslPolicy,
+};
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusExporter;
+pub use source::{DegradedSource, JobCounter, TracesetSource};
+pub use state_machine::{decide, AdapterState, Direction, Observations, Trend, SETTLE_TIMEOUT};
 pub use tracesets;
+pub use warm_start::{LearnedState, SizeEntry, StateParameters};
+pub use watchdog::HealthIssue;
 
+mod advisor;
+mod clock;
+mod config;
+mod coordinator;
 mod errors;
+mod events;
+mod expression;
+mod filters;
+mod guards;
 mod intervals;
+pub mod metrics;
+mod named_metrics;
 mod parameters;
+mod performance;
+mod policies;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod source;
+mod state_machine;
+mod statistics;
+mod warm_start;
+mod watchdog;
 
 pub struct ScalingAdapter {
     parameters: ScalingParameters,
-    traceset: Traceset,
+    traceset: Box<dyn TracesetSource>,
     state: AdapterState,
+    policy: Option<Box<dyn ScalingPolicy>>,
     metrics_history: MetricsHistory,
+    performance_table: PerformanceTable,
     latest_snapshot: TracesetSnapshot,
+    /// wall clock time of the latest snapshot, only used to label intervals
     latest_snapshot_time: SystemTime,
+    /// monotonic time of the latest snapshot
+    latest_snapshot_instant: Instant,
+    clock: Arc<dyn Clock>,
     recent_invalid_intervals: usize,
+    /// pool size at which a guard was violated and until when it must not be reached again
+    guard_limit: Option<(usize, Instant)>,
+    /// latest recommended pool size, None until the first advice
+    target_size: Option<usize>,
+    /// valid intervals whose pool size did not match the recommended size
+    unfollowed_advice: usize,
+    /// recommended size the pool did not reach in the latest interval
+    unfollowed_target: Option<usize>,
+    phase: ScalingPhase,
+    /// time and amount of targets after each change of the targets since the latest snapshot
+    target_changes: Vec<(Instant, usize)>,
+    /// latest interval was kept as mixed interval
+    recent_mixed_interval: bool,
+    /// pool size recommended by the restored state, None on a cold start
+    initial_size: Option<usize>,
+    /// running without kernel data, see ScalingAdapter::degraded
+    degraded: bool,
+    job_counter: JobCounter,
+    /// completed jobs at the time of the latest snapshot
+    latest_completed_jobs: u64,
+    /// queue size passed with the latest advice request
+    queue_size: i32,
+    /// issue the watchdog found in the latest checked interval, scaling is frozen while Some
+    health_issue: Option<HealthIssue>,
 }
 
 // synchronize access by wrapping with Arc<Mutex<_>>
 impl ScalingAdapter {
+    /// fails for invalid params (see ScalingParameters::validate)
+    /// or if the traceset cannot be created, unless params.degraded_fallback is set
     pub fn new(params: ScalingParameters) -> Result<ScalingAdapter, AdapterError> {
-        let traceset = Traceset::new(&Vec::new(), &params.syscall_nrs)
-            .ok_or(AdapterError::TracesetInitFailure)?;
+        params.validate().map_err(AdapterError::InvalidParameters)?;
+        match Traceset::new(&Vec::new(), &params.syscall_nrs) {
+            Some(traceset) => Ok(ScalingAdapter::from_source(params, Box::new(traceset))),
+            None if params.degraded_fallback => Ok(ScalingAdapter::degraded(params)),
+            None => Err(AdapterError::TracesetInitFailure),
+        }
+    }
+
+    /// adapter without kernel data: the scale metric is the job throughput reported
+    /// through job_counter, and scale ups are only advised while jobs are queued
+    /// named metrics and guards depend on kernel data and are dropped
+    /// will panic for invalid params, see ScalingParameters::validate
+    pub fn degraded(mut params: ScalingParameters) -> ScalingAdapter {
+        info!("DEGRADED: traceset unavailable, scaling by completed jobs and queue size");
+        params.calc_metrics = Box::new(metrics::completed_jobs_per_second);
+        params.scale_metric = Some(String::from("job_throughput"));
+        params.named_metrics = None;
+        params.guards.clear();
+        let mut adapter = ScalingAdapter::with_source(params, Box::new(DegradedSource::new()));
+        adapter.degraded = true;
+        adapter
+    }
+
+    /// create an adapter that reads its data from the given source instead of a kernel traceset
+    /// will panic for invalid params, see try_with_source
+    pub fn with_source(
+        params: ScalingParameters,
+        traceset: Box<dyn TracesetSource>,
+    ) -> ScalingAdapter {
+        ScalingAdapter::try_with_source(params, traceset)
+            .unwrap_or_else(|e| panic!("invalid scaling parameters: {:?}", e))
+    }
+
+    /// same as with_source, but returns an error for invalid params
+    pub fn try_with_source(
+        params: ScalingParameters,
+        traceset: Box<dyn TracesetSource>,
+    ) -> Result<ScalingAdapter, AdapterError> {
+        params.validate().map_err(AdapterError::InvalidParameters)?;
+        Ok(ScalingAdapter::from_source(params, traceset))
+    }
+
+    /// params must be validated
+    fn from_source(
+        mut params: ScalingParameters,
+        traceset: Box<dyn TracesetSource>,
+    ) -> ScalingAdapter {
         let initial_snapshot = traceset.get_snapshot();
+        // history must hold the samples of the latest and the previous pool size
+        let history_capacity = usize::max(20, 2 * params.samples_per_size);
+        let performance_table = PerformanceTable::new(params.aging_factor);
+        let policy = params.policy.take();
+        let clock = params.clock.clone();
         info!("_I_AdapterInit");
-        Ok(ScalingAdapter {
+        let mut adapter = ScalingAdapter {
             parameters: params,
             traceset,
             state: AdapterState::Startup,
-            metrics_history: MetricsHistory::new(),
+            policy,
+            metrics_history: MetricsHistory::with_capacity(history_capacity),
+            performance_table,
             latest_snapshot: initial_snapshot,
-            latest_snapshot_time: SystemTime::now(),
+            latest_snapshot_time: clock.wall_time(),
+            latest_snapshot_instant: clock.now(),
+            clock,
             recent_invalid_intervals: 0,
-        })
+            guard_limit: None,
+            target_size: None,
+            unfollowed_advice: 0,
+            unfollowed_target: None,
+            phase: ScalingPhase::Measuring,
+            target_changes: Vec::new(),
+            recent_mixed_interval: false,
+            initial_size: None,
+            degraded: false,
+            job_counter: JobCounter::default(),
+            latest_completed_jobs: 0,
+            queue_size: 0,
+            health_issue: None,
+        };
+        if let Some(state_file) = adapter.parameters.state_file.clone() {
+            match LearnedState::load(&state_file).and_then(|state| adapter.restore_state(&state)) {
+                Ok(initial_size) => info!("WARM START: initial pool size {}", initial_size),
+                Err(e) => info!("WARM START: cold start, {}", e),
+            }
+        }
+        adapter
+    }
+
+    /// learned state to be restored by a later run with the same parameters
+    pub fn export_state(&self) -> LearnedState {
+        LearnedState::new(
+            self.clock.wall_time(),
+            self.traceset.get_amount_targets(),
+            &self.parameters,
+            &self.performance_table,
+        )
+    }
+
+    pub fn save_state(&self, path: impl AsRef<Path>) -> Result<(), StateError> {
+        self.export_state().save(path)
+    }
+
+    /// use the state of an earlier run as prior, returns the pool size to start with
+    /// the adapter settles at that size instead of climbing from 1
+    pub fn restore_state(&mut self, state: &LearnedState) -> Result<usize, StateError> {
+        let max_age = Duration::from_millis(self.parameters.max_state_age_ms);
+        state.check(&self.parameters, self.clock.wall_time(), max_age)?;
+        state.restore_into(&mut self.performance_table);
+        let initial_size = state.initial_size(&self.parameters);
+        self.initial_size = Some(initial_size);
+        self.target_size = Some(initial_size);
+        self.state = Settled(self.clock.now() + SETTLE_TIMEOUT, Direction::Up);
+        Ok(initial_size)
+    }
+
+    /// pool size recommended by the restored state, None on a cold start
+    pub fn get_initial_size(&self) -> Option<usize> {
+        self.initial_size
     }
 
     pub fn add_tracee(&mut self, tracee_pid: i32) -> bool {
-        self.traceset.register_target(tracee_pid)
+        let is_added = self.traceset.register_target(tracee_pid);
+        if is_added {
+            self.record_target_change();
+        }
+        is_added
     }
 
     pub fn remove_tracee(&mut self, tracee_pid: i32) -> bool {
-        self.traceset.deregister_target(tracee_pid)
+        let is_removed = self.traceset.deregister_target(tracee_pid);
+        if is_removed {
+            self.record_target_change();
+        }
+        is_removed
+    }
+
+    fn record_target_change(&mut self) {
+        if self.parameters.mixed_intervals {
+            let amount_targets = self.traceset.get_amount_targets();
+            self.target_changes.push((self.clock.now(), amount_targets));
+        }
+    }
+
+    /// how long the pool had each amount of targets since the latest snapshot until end
+    fn target_timeline(&self, end: Instant) -> Vec<(usize, Duration)> {
+        let mut timeline = Vec::new();
+        let mut amount_targets = self.latest_snapshot.targets.len();
+        let mut since = self.latest_snapshot_instant;
+        for (instant, new_amount_targets) in self.target_changes.iter() {
+            let instant = (*instant).max(since).min(end);
+            timeline.push((amount_targets, instant - since));
+            amount_targets = *new_amount_targets;
+            since = instant;
+        }
+        timeline.push((amount_targets, end.saturating_duration_since(since)));
+        timeline
     }
 
     /// take new snapshot and take difference with previous snapshot
-    /// if interval is valid (amount of targets matches)
+    /// if interval is valid (amount of targets matches, or mixed intervals are kept)
+    /// and the pool is not settling and the watchdog (if enabled) accepts it
     ///      update history and return true
     /// else
     ///      return false
     pub fn update(&mut self) -> bool {
         let snapshot = self.traceset.get_snapshot();
-        let snapshot_time = SystemTime::now();
-        let interval_data = IntervalData::new(&self.latest_snapshot, &snapshot);
+        let completed_jobs = self.job_counter.get();
+        let snapshot_time = self.clock.wall_time();
+        let snapshot_instant = self.clock.now();
+        let interval_duration =
+            snapshot_instant.saturating_duration_since(self.latest_snapshot_instant);
+        let mut weight = 1.0;
+        let interval_data = if self.parameters.mixed_intervals {
+            let timeline = self.target_timeline(snapshot_instant);
+            let target_time = timeline
+                .iter()
+                .map(|(amount_targets, duration)| *duration * *amount_targets as u32)
+                .sum();
+            let data = IntervalData::new_mixed(
+                &self.latest_snapshot,
+                &snapshot,
+                interval_duration,
+                target_time,
+                &self.parameters.syscall_nrs,
+            );
+            if data.mixed && interval_duration.as_nanos() > 0 {
+                let time_at_size: Duration = timeline
+                    .iter()
+                    .filter(|(amount_targets, _)| *amount_targets == data.amount_targets)
+                    .map(|(_, duration)| *duration)
+                    .sum();
+                weight = time_at_size.as_secs_f64() / interval_duration.as_secs_f64();
+            }
+            Some(data)
+        } else {
+            IntervalData::new(
+                &self.latest_snapshot,
+                &snapshot,
+                interval_duration,
+                &self.parameters.syscall_nrs,
+            )
+        };
+        let interval_data = interval_data.map(|mut data| {
+            data.completed_jobs = completed_jobs.saturating_sub(self.latest_completed_jobs);
+            data
+        });
+        self.target_changes.clear();
+        self.recent_mixed_interval = false;
+        let health_issue = match &interval_data {
+            Some(data) if self.parameters.watchdog => self.check_health(&snapshot, data),
+            _ => None,
+        };
         let is_success = match interval_data {
+            Some(_) if health_issue.is_some() => {
+                debug!("UPDATE: {:?}, interval rejected by watchdog", health_issue);
+                self.health_issue = health_issue;
+                self.recent_invalid_intervals = 0;
+                false
+            }
+            // average pool size not reached during the interval, nothing to learn about it
+            Some(data) if data.mixed && weight == 0.0 => {
+                debug!(
+                    "UPDATE: mixed interval without time at size {}",
+                    data.amount_targets
+                );
+                self.recent_invalid_intervals += 1;
+                false
+            }
+            Some(data) if !data.mixed && self.is_settling() => {
+                debug!("UPDATE: pool settling, interval discarded");
+                self.recent_invalid_intervals = 0;
+                self.clear_health_issue();
+                false
+            }
             Some(data) => {
+                self.clear_health_issue();
                 debug!("UPDATE: {:?}", data);
-                let metrics = (self.parameters.calc_metrics)(&data);
+                let (metrics, named_values) = match &self.parameters.named_metrics {
+                    Some(named_metrics) => {
+                        let named_values = (named_metrics.calc)(&data);
+                        (named_metrics.derived_data(&named_values), named_values)
+                    }
+                    None => ((self.parameters.calc_metrics)(&data), Vec::new()),
+                };
+                let amount_targets = data.amount_targets;
+                let filtered_scale_metric = {
+                    let same_size_history: Vec<&IntervalMetrics> = self
+                        .metrics_history
+                        .last()
+                        .into_iter()
+                        .take_while(|previous| previous.amount_targets == amount_targets)
+                        .collect();
+                    self.parameters
+                        .metric_filter
+                        .apply(metrics.scale_metric, &same_size_history)
+                };
+                let named_metrics = self.parameters.named_metrics.as_ref();
+                let guard_values = self
+                    .parameters
+                    .guards
+                    .iter()
+                    .map(|guard| {
+                        let named_value = named_metrics
+                            .and_then(|named| named.value_of(&named_values, &guard.name));
+                        guard.value(&data, named_value)
+                    })
+                    .collect();
                 let history_point = IntervalMetrics {
                     derived_data: metrics,
-                    amount_targets: self.latest_snapshot.targets.len(),
+                    filtered_scale_metric,
+                    guard_values,
+                    named_values,
+                    amount_targets,
                     interval_start: self.latest_snapshot_time,
                     interval_end: snapshot_time,
+                    interval_duration,
+                    mixed: data.mixed,
+                    weight,
                 };
                 self.metrics_history.add(history_point);
+                self.performance_table
+                    .add_weighted(amount_targets, filtered_scale_metric, weight);
                 self.recent_invalid_intervals = 0;
+                self.recent_mixed_interval = data.mixed;
                 true
             }
             None => {
@@ -83,137 +399,499 @@ impl ScalingAdapter {
         };
         self.latest_snapshot = snapshot;
         self.latest_snapshot_time = snapshot_time;
+        self.latest_snapshot_instant = snapshot_instant;
+        self.latest_completed_jobs = completed_jobs;
         is_success
     }
 
+    /// watchdog checks of an interval ending with snapshot, None if it is plausible
+    fn check_health(
+        &self,
+        snapshot: &TracesetSnapshot,
+        data: &IntervalData,
+    ) -> Option<HealthIssue> {
+        let max_duration = Duration::from_millis(
+            (self.parameters.check_interval_ms as f64
+                * self.parameters.watchdog_max_interval_factor) as u64,
+        );
+        let busy = self.queue_size > 0 || data.completed_jobs > 0;
+        watchdog::check_interval(
+            &self.latest_snapshot,
+            snapshot,
+            data,
+            busy,
+            !self.degraded,
+            max_duration,
+        )
+    }
+
+    fn clear_health_issue(&mut self) {
+        if let Some(issue) = self.health_issue.take() {
+            info!("WATCHDOG: recovered from {:?}, scaling resumed", issue);
+        }
+    }
+
+    /// called for a valid interval ending now, counts it as discarded if the pool is still settling
+    fn is_settling(&mut self) -> bool {
+        if let ScalingPhase::ActionPending { since, discarded } = &mut self.phase {
+            let settling_period = Duration::from_millis(self.parameters.settling_ms);
+            if *discarded < self.parameters.settling_intervals
+                || self.latest_snapshot_instant < *since + settling_period
+            {
+                *discarded += 1;
+                return true;
+            }
+            self.phase = ScalingPhase::Measuring;
+        }
+        false
+    }
+
     pub fn get_latest_metrics(&self) -> Option<&IntervalMetrics> {
         self.metrics_history.last().get(0).copied()
     }
 
-    fn scaling_advice_startup(&mut self) -> i32 {
-        self.state = AdapterState::Scaling(1);
-        1
+    /// names of the named metrics, empty if no named metrics are configured
+    pub fn get_metric_names(&self) -> &[String] {
+        match &self.parameters.named_metrics {
+            Some(named_metrics) => &named_metrics.names,
+            None => &[],
+        }
     }
 
-    fn scaling_advice_settled(&mut self, last_direction: Direction) -> i32 {
-        match last_direction {
-            Direction::Up => {
-                debug!("{}", "Exploring DOWN");
-                self.state = AdapterState::Exploring(Direction::Down);
-                -1
-            }
-            Direction::Down => {
-                debug!("{}", "Exploring UP");
-                self.state = AdapterState::Exploring(Direction::Up);
-                1
+    /// value of the named metric in the latest valid interval
+    pub fn get_latest_named_metric(&self, name: &str) -> Option<f64> {
+        let named_metrics = self.parameters.named_metrics.as_ref()?;
+        let latest = self.metrics_history.get(0)?;
+        named_metrics.value_of(&latest.named_values, name)
+    }
+
+    /// amount of registered targets (pool size as seen by the adapter)
+    pub fn get_amount_targets(&self) -> usize {
+        self.traceset.get_amount_targets()
+    }
+
+    /// point in time (of the adapter clock) at which get_scaling_advice starts the next interval
+    pub fn next_interval_due(&self) -> Instant {
+        self.latest_snapshot_instant + Duration::from_millis(self.parameters.check_interval_ms)
+    }
+
+    /// absolute alternative to get_scaling_advice: recommended pool size
+    /// does not depend on earlier advice having been applied exactly once,
+    /// callers converge to it by comparing with their own size
+    /// None until the first valid interval
+    pub fn get_target_size(&mut self, queue_size: i32) -> Option<usize> {
+        self.get_scaling_advice(queue_size);
+        self.target_size
+    }
+
+    /// how often the pool size of a valid interval differed from the recommended size
+    pub fn get_unfollowed_advice_count(&self) -> usize {
+        self.unfollowed_advice
+    }
+
+    /// running without kernel data because the traceset was unavailable
+    pub fn is_degraded(&self) -> bool {
+        self.degraded
+    }
+
+    /// handle for the pool to report completed jobs, used by the job_throughput metric
+    pub fn job_counter(&self) -> JobCounter {
+        self.job_counter.clone()
+    }
+
+    /// issue the watchdog found in the latest interval, scaling is frozen while Some
+    pub fn get_health_issue(&self) -> Option<HealthIssue> {
+        self.health_issue
+    }
+
+    /// whether the adapter waits for the pool to settle after its latest advice
+    pub fn get_phase(&self) -> ScalingPhase {
+        self.phase
+    }
+
+    /// observed scale metric statistics per pool size
+    pub fn get_performance_table(&self) -> &PerformanceTable {
+        &self.performance_table
+    }
+
+    /// configured scaling policy, if it is of type P
+    pub fn get_policy<P: ScalingPolicy + 'static>(&self) -> Option<&P> {
+        self.policy
+            .as_ref()
+            .and_then(|policy| policy.as_any().downcast_ref::<P>())
+    }
+
+    /// filtered scale metrics of the latest intervals with the same amount of targets as the latest one,
+    /// followed by the scale metrics of the intervals before them (same rule)
+    /// both groups contain at most samples_per_size values, newest first
+    fn latest_samples(&self) -> (Vec<f64>, Vec<f64>) {
+        let max_samples = self.parameters.samples_per_size;
+        let history = self.metrics_history.last();
+        let take_group = |start: usize| -> Vec<f64> {
+            match history.get(start) {
+                Some(first) => history[start..]
+                    .iter()
+                    .take_while(|metrics| metrics.amount_targets == first.amount_targets)
+                    .take(max_samples)
+                    .map(|metrics| metrics.filtered_scale_metric)
+                    .collect(),
+                None => Vec::new(),
             }
-        }
+        };
+        let latest = take_group(0);
+        let previous = take_group(latest.len());
+        (latest, previous)
+    }
+
+    /// true if enough samples of the latest pool size have been collected for a comparison
+    fn has_enough_samples(&self) -> bool {
+        self.latest_samples().0.len() >= self.parameters.samples_per_size
     }
 
-    fn scaling_advice_exploring(&mut self, direction: Direction) -> i32 {
-        // compare latest interval with previous
-        // metrics_history must already contain 2 entries
-        let latest = self.metrics_history.get(0).unwrap();
-        let previous = self.metrics_history.get(1).unwrap();
-        let step_size = match direction {
-            Direction::Up => 1,
-            Direction::Down => -1,
+    /// compare the latest samples with the previous ones using the configured method
+    fn compare_latest_samples(&self) -> Trend {
+        let (latest, previous) = self.latest_samples();
+        let trend = match self.parameters.comparison {
+            ComparisonMethod::StabilityFactor => {
+                let latest_mean = statistics::mean(&latest);
+                let previous_mean = statistics::mean(&previous);
+                let stability_factor = self.parameters.stability_factor;
+                if latest_mean * stability_factor > previous_mean {
+                    Trend::Improved
+                } else if previous_mean * stability_factor > latest_mean {
+                    Trend::Degraded
+                } else {
+                    Trend::Unchanged
+                }
+            }
+            ComparisonMethod::WelchTTest => self.trend_from_test(
+                statistics::welch_t_test_greater(&latest, &previous),
+                statistics::welch_t_test_greater(&previous, &latest),
+            ),
+            ComparisonMethod::MannWhitneyU => self.trend_from_test(
+                statistics::mann_whitney_u_greater(&latest, &previous),
+                statistics::mann_whitney_u_greater(&previous, &latest),
+            ),
         };
-        // enter scaling state
-        if latest.derived_data.scale_metric * self.parameters.stability_factor
-            > previous.derived_data.scale_metric
-        {
-            self.state = AdapterState::Scaling(step_size);
-            step_size
-        // scale back to previous & enter settled state
-        // set timeout for next explore move
+        debug!(
+            "ADVICE: compared {} latest with {} previous samples: {:?}",
+            latest.len(),
+            previous.len(),
+            trend
+        );
+        trend
+    }
+
+    fn trend_from_test(&self, p_value_greater: f64, p_value_less: f64) -> Trend {
+        let significance_level = self.parameters.significance_level;
+        if p_value_greater < significance_level {
+            Trend::Improved
+        } else if p_value_less < significance_level {
+            Trend::Degraded
         } else {
-            self.state = Settled(
-                SystemTime::now()
-                    .checked_add(Duration::from_millis(2000))
-                    .unwrap(),
-                direction,
-            );
-            -step_size
+            Trend::Unchanged
         }
     }
 
-    fn scaling_advice_scaling(&mut self, step_size: i32) -> i32 {
-        // compare latest interval with previous
-        // metrics_history must already contain 2 entries
-        let latest = self.metrics_history.get(0).unwrap();
-        let previous = self.metrics_history.get(1).unwrap();
-        let direction = Direction::from_step_size(step_size);
-        // step sizes will always grow 1 -> 2 -> 4
-        let new_step_size = if step_size.abs() < 4 {
-            step_size + 1
-        } else {
-            step_size
+    /// if the current pool size performs worse than the best known one, jump to that size
+    /// and settle there, returns None if the current pool size is fine
+    fn scaling_advice_jump_to_best(&mut self) -> Option<i32> {
+        let current_size = self.metrics_history.get(0)?.amount_targets;
+        let (best_size, best_statistics) = self.performance_table.best()?;
+        if best_size == current_size {
+            return None;
+        }
+        let current_mean = statistics::mean(&self.latest_samples().0);
+        if current_mean * self.parameters.stability_factor >= best_statistics.mean {
+            return None;
+        }
+        let advice = best_size as i32 - current_size as i32;
+        self.state = Settled(
+            self.clock.now() + SETTLE_TIMEOUT,
+            Direction::from_step_size(advice),
+        );
+        Some(advice)
+    }
+
+    /// advice of the built-in state machine
+    fn state_machine_advice(&mut self) -> (i32, AdviceReason) {
+        // comparisons need enough samples of the current pool size,
+        // startup collects them too, so the first comparison has a full previous group
+        let is_comparing = matches!(
+            self.state,
+            AdapterState::Scaling(_) | AdapterState::Exploring(_)
+        );
+        let needs_samples = is_comparing || self.state == AdapterState::Startup;
+        if needs_samples && !self.has_enough_samples() {
+            return (0, AdviceReason::CollectingSamples);
+        }
+        let now = self.clock.now();
+        let jump_advice = match self.state {
+            AdapterState::Startup => None,
+            _ if self.parameters.jump_to_best => self.scaling_advice_jump_to_best(),
+            _ => None,
         };
-        // scale further
-        if latest.derived_data.scale_metric * self.parameters.stability_factor
-            > previous.derived_data.scale_metric
-        {
-            self.state = AdapterState::Scaling(new_step_size);
-            new_step_size
-        // scale back to previous & enter settled state
-        // set no timeout, so next action will be exploring step
-        } else if previous.derived_data.scale_metric * self.parameters.stability_factor
-            > latest.derived_data.scale_metric
-            && self.traceset.get_amount_targets() > 1
-        {
-            self.state = Settled(SystemTime::now(), direction);
-            -step_size
-        // enter settled state
+        if let Some(advice) = jump_advice {
+            return (advice, AdviceReason::JumpToBest);
+        }
+        let observations = Observations {
+            now,
+            amount_targets: self.traceset.get_amount_targets(),
+            trend: if is_comparing {
+                self.compare_latest_samples()
+            } else {
+                Trend::Unchanged
+            },
+        };
+        let (state, advice) = state_machine::decide(self.state, &observations);
+        self.state = state;
+        (advice, AdviceReason::StateMachine)
+    }
+
+    /// names of the guards violated in the latest valid interval
+    fn violated_guards(&self) -> Vec<String> {
+        match self.metrics_history.get(0) {
+            Some(latest) => self
+                .parameters
+                .guards
+                .iter()
+                .zip(latest.guard_values.iter())
+                .filter(|(guard, value)| guard.is_violated(**value))
+                .map(|(guard, _value)| guard.name.clone())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// back off if the latest interval violates a guard metric
+    /// otherwise reject growing the pool to a size at which a guard was violated recently
+    fn guarded_advice(
+        &mut self,
+        advice: i32,
+        reason: AdviceReason,
+        current_size: usize,
+    ) -> (i32, AdviceReason) {
+        let now = self.clock.now();
+        let settled_timeout = now + SETTLE_TIMEOUT;
+        if !self.violated_guards().is_empty() {
+            let backoff = Duration::from_millis(self.parameters.guard_backoff_ms);
+            self.guard_limit = Some((current_size, now + backoff));
+            // next exploration step is away from the violation
+            self.state = Settled(settled_timeout, Direction::Up);
+            return (-1, AdviceReason::GuardViolated);
+        }
+        if let Some((_limit, expiry)) = self.guard_limit {
+            if now > expiry {
+                self.guard_limit = None;
+            }
+        }
+        match self.guard_limit {
+            Some((limit, _expiry)) if advice > 0 && current_size + advice as usize >= limit => {
+                let allowed_advice = i32::max(limit as i32 - 1 - current_size as i32, 0);
+                if allowed_advice == 0 {
+                    self.state = Settled(settled_timeout, Direction::Up);
+                }
+                (allowed_advice, AdviceReason::GuardLimit)
+            }
+            _ => (advice, reason),
+        }
+    }
+
+    /// restrict the advice so the resulting pool size stays within the configured bounds
+    /// advice of 0 stays 0, even if the current pool size is out of bounds
+    fn bounded_advice(&self, advice: i32, current_size: usize) -> i32 {
+        let current_size = current_size as i32;
+        let min_size = self.parameters.min_size as i32;
+        // unbounded by default
+        let max_size = self.parameters.max_size.min(i32::MAX as usize) as i32;
+        if advice > 0 {
+            advice.min(i32::max(max_size - current_size, 0))
+        } else if advice < 0 {
+            advice.max(-i32::max(current_size - min_size, 0))
         } else {
-            self.state = Settled(SystemTime::now(), direction);
             0
         }
     }
 
     pub fn get_scaling_advice(&mut self, queue_size: i32) -> i32 {
-        let now = SystemTime::now();
-        let elapsed = now
-            .duration_since(self.latest_snapshot_time)
-            .map(|duration| duration.as_millis())
-            .unwrap_or(0);
-        if elapsed >= self.parameters.check_interval_ms as u128 {
-            info!("ADVICE: new advice, enough time elapsed");
-            info!("_I_QSIZE: {}", queue_size);
-            self.update();
-            // if latest interval not valid (amount targets changed)
-            if self.recent_invalid_intervals > 0 {
-                info!("ADVICE: invalid interval (targets changed), advice 0");
-                return 0;
+        let elapsed = self
+            .clock
+            .now()
+            .saturating_duration_since(self.latest_snapshot_instant)
+            .as_millis();
+        if elapsed < self.parameters.check_interval_ms as u128 {
+            return 0;
+        }
+        let previous_state = self.state;
+        self.queue_size = queue_size;
+        self.unfollowed_target = None;
+        self.update();
+        // if latest interval not valid (amount targets changed)
+        if self.recent_invalid_intervals > 0 {
+            self.notify_observers(previous_state, queue_size, 0, AdviceReason::InvalidInterval);
+            return 0;
+        }
+        if let Some(issue) = self.health_issue {
+            return self.watchdog_advice(issue, previous_state, queue_size);
+        }
+        // mixed intervals are learned from, but decisions need an interval at a constant size
+        if self.recent_mixed_interval {
+            self.notify_observers(previous_state, queue_size, 0, AdviceReason::MixedInterval);
+            return 0;
+        }
+        if let ScalingPhase::ActionPending { .. } = self.phase {
+            self.notify_observers(previous_state, queue_size, 0, AdviceReason::Settling);
+            return 0;
+        }
+        let amount_targets = self.latest_snapshot.targets.len();
+        if let Some(target_size) = self.target_size {
+            // a whole interval at another size: advice was lost, ignored or capped by the pool
+            if target_size != amount_targets {
+                self.unfollowed_advice += 1;
+                self.unfollowed_target = Some(target_size);
+                self.target_size = Some(amount_targets);
             }
-            info!("ADVICE: current state: {:?}", self.state);
-            let advice = match self.state {
-                AdapterState::Startup => return self.scaling_advice_startup(),
-                AdapterState::Settled(timeout, direction) => {
-                    if SystemTime::now() > timeout {
-                        self.scaling_advice_settled(direction)
-                    } else {
-                        0
-                    }
-                }
-                AdapterState::Scaling(i) => self.scaling_advice_scaling(i),
-                AdapterState::Exploring(direction) => self.scaling_advice_exploring(direction),
-            };
-            info!("ADVICE: new state: {:?}", self.state);
-            // at least one recent interval must exists, as we are not in startup state anymore
-            let latest_interval = self.metrics_history.get(0).unwrap();
-            let interval_duration = latest_interval.end_millis() - latest_interval.start_millis();
-            let amount_targets = latest_interval.amount_targets;
-            let m1 = latest_interval.derived_data.scale_metric;
-            let m2 = latest_interval.derived_data.reset_metric;
-            debug!("ADVICE: last interval ms: {}", interval_duration);
-            info!("_I_PSIZE: {}", amount_targets);
-            info!("_I_M1_VAL: {}", m1);
-            info!("_I_M2_VAL: {}", m2);
-            info!("ADVICE: {}", advice);
-            advice
+        }
+        let (advice, reason) = match self.policy.as_mut() {
+            Some(policy) => {
+                let context = PolicyContext {
+                    history: self.metrics_history.last(),
+                    performance: &self.performance_table,
+                    amount_targets,
+                    min_size: self.parameters.min_size,
+                    max_size: self.parameters.max_size,
+                    queue_size,
+                    metric_names: match &self.parameters.named_metrics {
+                        Some(named_metrics) => &named_metrics.names,
+                        None => &[],
+                    },
+                    now: self.clock.now(),
+                };
+                (policy.scaling_advice(&context), AdviceReason::Policy)
+            }
+            None => self.state_machine_advice(),
+        };
+        let (advice, reason) = self.guarded_advice(advice, reason, amount_targets);
+        // without kernel data, more workers only help if jobs are waiting
+        let (advice, reason) = if self.degraded && advice > 0 && queue_size <= 0 {
+            debug!("ADVICE: degraded, scale up rejected, queue empty");
+            // like a rejection by a guard, settle instead of stepping on from the current state
+            self.state = Settled(self.clock.now() + SETTLE_TIMEOUT, Direction::Up);
+            (0, AdviceReason::QueueEmpty)
         } else {
-            0
+            (advice, reason)
+        };
+        let bounded_advice = self.bounded_advice(advice, amount_targets);
+        let reason = if bounded_advice != advice {
+            AdviceReason::Bounded
+        } else {
+            reason
+        };
+        if bounded_advice != 0 || self.target_size.is_none() {
+            self.target_size = Some((amount_targets as i32 + bounded_advice).max(0) as usize);
+        }
+        if bounded_advice != 0 {
+            // settling period starts with the next interval
+            self.phase = ScalingPhase::ActionPending {
+                since: self.latest_snapshot_instant,
+                discarded: 0,
+            };
+        }
+        self.notify_observers(previous_state, queue_size, bounded_advice, reason);
+        bounded_advice
+    }
+
+    /// scaling is frozen while the watchdog rejects the intervals,
+    /// the pool is pinned to the fallback size if one is configured
+    fn watchdog_advice(
+        &mut self,
+        issue: HealthIssue,
+        previous_state: AdapterState,
+        queue_size: i32,
+    ) -> i32 {
+        let amount_targets = self.latest_snapshot.targets.len();
+        let advice = match self.parameters.watchdog_fallback_size {
+            Some(size) => self.bounded_advice(size as i32 - amount_targets as i32, amount_targets),
+            None => 0,
+        };
+        let size = (amount_targets as i32 + advice).max(0) as usize;
+        self.target_size = Some(size);
+        let event = WatchdogEvent {
+            timestamp: self.clock.wall_time(),
+            issue,
+            amount_targets,
+            queue_size,
+            pinned_size: self.parameters.watchdog_fallback_size.map(|_| size),
+        };
+        for observer in self.parameters.observers.iter() {
+            observer.on_watchdog(&event);
+        }
+        self.notify_observers(previous_state, queue_size, advice, AdviceReason::Watchdog);
+        advice
+    }
+
+    /// metrics of the latest valid interval, with the names of the guard and named metrics
+    fn latest_interval_summary(&self) -> Option<IntervalSummary> {
+        let latest = self.metrics_history.get(0)?;
+        let guard_values = self
+            .parameters
+            .guards
+            .iter()
+            .zip(latest.guard_values.iter())
+            .map(|(guard, value)| (guard.name.clone(), *value))
+            .collect();
+        let named_values = self
+            .get_metric_names()
+            .iter()
+            .zip(latest.named_values.iter())
+            .map(|(name, value)| (name.clone(), *value))
+            .collect();
+        Some(IntervalSummary {
+            pool_size: latest.amount_targets,
+            duration: latest.interval_duration,
+            scale_metric: latest.derived_data.scale_metric,
+            filtered_scale_metric: latest.filtered_scale_metric,
+            reset_metric: latest.derived_data.reset_metric,
+            guard_values,
+            named_values,
+        })
+    }
+
+    fn notify_observers(
+        &self,
+        previous_state: AdapterState,
+        queue_size: i32,
+        advice: i32,
+        reason: AdviceReason,
+    ) {
+        let interval = match reason {
+            AdviceReason::InvalidInterval => None,
+            _ => self.latest_interval_summary(),
+        };
+        let event = AdviceEvent {
+            timestamp: self.clock.wall_time(),
+            previous_state,
+            new_state: self.state,
+            queue_size,
+            traceset: TracesetCounters {
+                read_bytes: self.latest_snapshot.read_bytes,
+                write_bytes: self.latest_snapshot.write_bytes,
+                blkio_delay: self.latest_snapshot.blkio_delay,
+                amount_targets: self.latest_snapshot.targets.len(),
+            },
+            interval,
+            advice,
+            reason,
+            degraded: self.degraded,
+            unfollowed_target: self.unfollowed_target,
+            violated_guards: match reason {
+                AdviceReason::InvalidInterval => Vec::new(),
+                _ => self.violated_guards(),
+            },
+            guard_limit: self.guard_limit.map(|(limit, _expiry)| limit),
+        };
+        for observer in self.parameters.observers.iter() {
+            observer.on_advice(&event);
         }
     }
 }
@@ -227,7 +905,10 @@ struct MetricsHistory {
 
 impl MetricsHistory {
     pub fn new() -> Self {
-        let capacity = 20;
+        MetricsHistory::with_capacity(20)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
         MetricsHistory {
             capacity,
             buffer: Vec::with_capacity(capacity),
@@ -293,40 +974,20 @@ impl MetricsHistory {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    Up,
-    Down,
-}
-
-impl Direction {
-    pub fn get_opposite(&self) -> Direction {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Down => Direction::Up,
-        }
-    }
-
-    pub fn from_step_size(step_size: i32) -> Direction {
-        if step_size >= 0 {
-            Direction::Up
-        } else {
-            Direction::Down
-        }
-    }
-}
-
-#[derive(Debug)]
-enum AdapterState {
-    Startup,
-    Scaling(i32),
-    Exploring(Direction),
-    Settled(SystemTime, Direction),
+/// phase of the adapter regarding its latest scaling action
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalingPhase {
+    /// waiting for the pool to reach the advised size and settle, intervals are discarded
+    /// since: start of the first interval after the action
+    ActionPending { since: Instant, discarded: usize },
+    /// intervals are measured and used for decisions
+    Measuring,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::source::TestSource;
     use env_logger::Env;
     use std::{sync::Once, time::Duration};
     use std::{thread, time};
@@ -345,15 +1006,8 @@ mod tests {
     fn construct_dummy_history_big() -> MetricsHistory {
         let mut result = MetricsHistory::new();
         for i in 1..25 {
-            let dummy = IntervalMetrics {
-                derived_data: IntervalDerivedData {
-                    scale_metric: i as f64,
-                    reset_metric: i as f64,
-                },
-                amount_targets: i,
-                interval_start: SystemTime::now(),
-                interval_end: SystemTime::now(),
-            };
+            let mut dummy = IntervalMetrics::for_test(i, i as f64);
+            dummy.derived_data.reset_metric = i as f64;
             result.add(dummy);
         }
         result
@@ -453,4 +1107,172 @@ mod tests {
         let is_removed = adapter.remove_tracee(echoer_pid as i32);
         assert!(is_removed);
     }
+
+    #[test]
+    fn invalid_parameters_rejected() {
+        let params = ScalingParameters::default().with_bounds(4, 2);
+        let source = TestSource::idle();
+        let result = ScalingAdapter::try_with_source(params, Box::new(source));
+        assert!(matches!(
+            result,
+            Err(AdapterError::InvalidParameters(ConfigError::InvalidValue {
+                parameter: "min_size",
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn target_size_not_followed() {
+        let clock = Arc::new(ManualClock::new());
+        let params = ScalingParameters::default().with_clock(clock.clone());
+        let source = TestSource::idle();
+        let mut adapter = ScalingAdapter::with_source(params, Box::new(source));
+        adapter.add_tracee(1);
+        let interval = Duration::from_millis(ScalingParameters::default().check_interval_ms);
+        // first interval invalid, targets changed
+        clock.advance(interval);
+        assert_eq!(adapter.get_target_size(0), None);
+        // startup scales up by one
+        clock.advance(interval);
+        assert_eq!(adapter.get_target_size(0), Some(2));
+        assert_eq!(adapter.get_unfollowed_advice_count(), 0);
+        // asking again within the interval does not change the target
+        assert_eq!(adapter.get_target_size(0), Some(2));
+        // pool stays at size 1 for a whole interval
+        clock.advance(interval);
+        adapter.get_target_size(0);
+        assert_eq!(adapter.get_unfollowed_advice_count(), 1);
+    }
+
+    #[test]
+    fn degraded_mode() {
+        let clock = Arc::new(ManualClock::new());
+        let (observer, events) = ChannelObserver::new();
+        let params = ScalingParameters::default()
+            .with_clock(clock.clone())
+            .with_observer(Box::new(observer));
+        let mut adapter = ScalingAdapter::degraded(params);
+        assert!(adapter.is_degraded());
+        let jobs = adapter.job_counter();
+        let interval = Duration::from_millis(ScalingParameters::default().check_interval_ms);
+        adapter.add_tracee(1);
+        clock.advance(interval);
+        adapter.get_scaling_advice(0);
+        for _ in 0..10 {
+            jobs.job_completed();
+        }
+        clock.advance(interval);
+        // startup would scale up, but no jobs are waiting
+        assert_eq!(adapter.get_scaling_advice(0), 0);
+        let event = events.try_iter().last().unwrap();
+        assert_eq!(event.reason, AdviceReason::QueueEmpty);
+        assert!(matches!(event.new_state, Settled(_, Direction::Up)));
+        assert!(event.degraded);
+        let latest_metrics = adapter.get_latest_metrics().unwrap();
+        assert!((latest_metrics.derived_data.scale_metric - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn watchdog_zero_counters() {
+        let clock = Arc::new(ManualClock::new());
+        let (observer, events) = ChannelObserver::new();
+        let params = ScalingParameters::default()
+            .with_clock(clock.clone())
+            .with_watchdog(5.0, Some(2))
+            .with_observer(Box::new(observer));
+        let source = TestSource::idle();
+        let mut adapter = ScalingAdapter::with_source(params, Box::new(source));
+        let interval = Duration::from_millis(ScalingParameters::default().check_interval_ms);
+        adapter.add_tracee(1);
+        clock.advance(interval);
+        adapter.get_scaling_advice(5);
+        // jobs are queued, but the targets did no io at all: pin to the fallback size
+        clock.advance(interval);
+        assert_eq!(adapter.get_scaling_advice(5), 1);
+        assert_eq!(adapter.get_health_issue(), Some(HealthIssue::ZeroCounters));
+        assert_eq!(adapter.get_target_size(5), Some(2));
+        let event = events.try_iter().last().unwrap();
+        assert_eq!(event.reason, AdviceReason::Watchdog);
+        assert!(adapter.get_performance_table().get(1).is_none());
+        adapter.add_tracee(2);
+        clock.advance(interval);
+        adapter.get_scaling_advice(0);
+        // idle pool without io is plausible
+        clock.advance(interval);
+        adapter.get_scaling_advice(0);
+        assert_eq!(adapter.get_health_issue(), None);
+        // after a long pause, scaling is frozen
+        clock.advance(interval * 10);
+        assert_eq!(adapter.get_scaling_advice(0), 0);
+        assert_eq!(
+            adapter.get_health_issue(),
+            Some(HealthIssue::LongInterval(interval * 10))
+        );
+    }
+
+    #[test]
+    fn settling_after_action() {
+        let clock = Arc::new(ManualClock::new());
+        let params = ScalingParameters::default()
+            .with_clock(clock.clone())
+            .with_settling(2, 0);
+        let source = TestSource::idle();
+        let mut adapter = ScalingAdapter::with_source(params, Box::new(source));
+        let interval = Duration::from_millis(ScalingParameters::default().check_interval_ms);
+        adapter.add_tracee(1);
+        clock.advance(interval);
+        adapter.get_scaling_advice(0);
+        clock.advance(interval);
+        assert_eq!(adapter.get_scaling_advice(0), 1);
+        assert!(matches!(
+            adapter.get_phase(),
+            ScalingPhase::ActionPending { discarded: 0, .. }
+        ));
+        adapter.add_tracee(2);
+        // invalid interval, does not count as settling interval
+        clock.advance(interval);
+        assert_eq!(adapter.get_scaling_advice(0), 0);
+        for discarded in 1..=2 {
+            clock.advance(interval);
+            assert_eq!(adapter.get_scaling_advice(0), 0);
+            assert!(matches!(
+                adapter.get_phase(),
+                ScalingPhase::ActionPending { discarded: d, .. } if d == discarded
+            ));
+            assert_eq!(adapter.get_latest_metrics().unwrap().amount_targets, 1);
+        }
+        clock.advance(interval);
+        adapter.get_scaling_advice(0);
+        assert_eq!(adapter.get_latest_metrics().unwrap().amount_targets, 2);
+    }
+
+    #[test]
+    fn mixed_interval() {
+        let clock = Arc::new(ManualClock::new());
+        let params = ScalingParameters::default()
+            .with_clock(clock.clone())
+            .with_mixed_intervals(true);
+        let source = TestSource::idle();
+        let mut adapter = ScalingAdapter::with_source(params, Box::new(source));
+        let interval = Duration::from_millis(ScalingParameters::default().check_interval_ms);
+        adapter.add_tracee(1);
+        clock.advance(interval);
+        assert!(adapter.update());
+        // second target joins halfway: 1.5 targets on average, attributed to size 2
+        clock.advance(interval / 2);
+        adapter.add_tracee(2);
+        clock.advance(interval / 2);
+        assert_eq!(adapter.get_scaling_advice(0), 0);
+        let latest = adapter.get_latest_metrics().unwrap();
+        assert!(latest.mixed);
+        assert_eq!(latest.amount_targets, 2);
+        assert!((latest.weight - 0.5).abs() < 1e-9);
+        let statistics = adapter.get_performance_table().get(2).unwrap();
+        assert!((statistics.weight - 0.5).abs() < 1e-9);
+        // constant size again
+        clock.advance(interval);
+        assert!(adapter.update());
+        assert!(!adapter.get_latest_metrics().unwrap().mixed);
+    }
 }
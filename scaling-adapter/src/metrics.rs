@@ -0,0 +1,136 @@
+// catalog of metric functions that can be used as calc_metrics
+// every function returns the same value as scale and reset metric
+
+use log::debug;
+
+use crate::intervals::{IntervalData, IntervalDerivedData};
+
+pub type CalcMetrics = Box<dyn Fn(&IntervalData) -> IntervalDerivedData + Send + Sync>;
+
+/// names of the metric functions, for selection by name
+pub const METRICS: [&str; 7] = [
+    "throughput",
+    "read_throughput",
+    "write_throughput",
+    "syscalls_per_second",
+    "inverse_blkio_delay",
+    "bytes_per_syscall_time",
+    "job_throughput",
+];
+
+/// metric function of the catalog by name
+pub fn metric_function(name: &str) -> Option<CalcMetrics> {
+    let function: fn(&IntervalData) -> IntervalDerivedData = match name {
+        "throughput" => read_write_bytes_per_ms,
+        "read_throughput" => read_bytes_per_ms,
+        "write_throughput" => written_bytes_per_ms,
+        "syscalls_per_second" => syscalls_per_second,
+        "inverse_blkio_delay" => inverse_blkio_delay,
+        "bytes_per_syscall_time" => bytes_per_syscall_time,
+        "job_throughput" => completed_jobs_per_second,
+        _ => return None,
+    };
+    Some(Box::new(function))
+}
+
+fn derived(value: f64) -> IntervalDerivedData {
+    IntervalDerivedData {
+        scale_metric: value,
+        reset_metric: value,
+    }
+}
+
+// conversion to f64 precise for durations under 1000 years for sure
+// a zero length interval is treated as 1 ms long
+fn duration_ms(data: &IntervalData) -> f64 {
+    data.duration_millis().max(1) as f64
+}
+
+/// read and written bytes per ms
+pub fn read_write_bytes_per_ms(data: &IntervalData) -> IntervalDerivedData {
+    derived((data.read_bytes + data.write_bytes) as f64 / duration_ms(data))
+}
+
+/// read bytes per ms
+pub fn read_bytes_per_ms(data: &IntervalData) -> IntervalDerivedData {
+    derived(data.read_bytes as f64 / duration_ms(data))
+}
+
+/// written bytes per ms
+pub fn written_bytes_per_ms(data: &IntervalData) -> IntervalDerivedData {
+    let write_bytes_per_ms = data.write_bytes as f64 / duration_ms(data);
+    debug!("{} MB/sec written in interval", write_bytes_per_ms / 1000.0);
+    derived(write_bytes_per_ms)
+}
+
+/// calls of all traced syscalls per second
+pub fn syscalls_per_second(data: &IntervalData) -> IntervalDerivedData {
+    let calls: u64 = data
+        .syscalls_data
+        .iter()
+        .map(|syscall| syscall.count as u64)
+        .sum();
+    derived(calls as f64 * 1000.0 / duration_ms(data))
+}
+
+/// 1 / (1 + blkio delay per ms of the interval), 1 without any delay
+pub fn inverse_blkio_delay(data: &IntervalData) -> IntervalDerivedData {
+    derived(1.0 / (1.0 + data.blkio_delay as f64 / duration_ms(data)))
+}
+
+/// read and written bytes per unit of time spent in the traced syscalls
+/// 0 if no time was spent in the traced syscalls
+pub fn bytes_per_syscall_time(data: &IntervalData) -> IntervalDerivedData {
+    let syscall_time: u64 = data
+        .syscalls_data
+        .iter()
+        .map(|syscall| syscall.total_time)
+        .sum();
+    if syscall_time == 0 {
+        return derived(0.0);
+    }
+    derived((data.read_bytes + data.write_bytes) as f64 / syscall_time as f64)
+}
+
+/// jobs completed per second, needs no kernel data but the pool must report completed jobs
+pub fn completed_jobs_per_second(data: &IntervalData) -> IntervalDerivedData {
+    derived(data.completed_jobs as f64 * 1000.0 / duration_ms(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tracesets::SyscallData;
+
+    #[test]
+    fn catalog() {
+        let syscalls_data = vec![
+            SyscallData {
+                count: 20,
+                total_time: 200,
+            },
+            SyscallData {
+                count: 30,
+                total_time: 300,
+            },
+        ];
+        let data = IntervalData {
+            read_bytes: 1000,
+            write_bytes: 4000,
+            blkio_delay: 500,
+            completed_jobs: 150,
+            ..IntervalData::for_test(Duration::from_millis(500), 2, syscalls_data)
+        };
+        let value = |name: &str| (metric_function(name).unwrap())(&data).scale_metric;
+        assert_eq!(value("throughput"), 10.0);
+        assert_eq!(value("read_throughput"), 2.0);
+        assert_eq!(value("write_throughput"), 8.0);
+        assert_eq!(value("syscalls_per_second"), 100.0);
+        assert_eq!(value("inverse_blkio_delay"), 0.5);
+        assert_eq!(value("bytes_per_syscall_time"), 10.0);
+        assert_eq!(value("job_throughput"), 300.0);
+        assert!(METRICS.iter().all(|name| metric_function(name).is_some()));
+        assert!(metric_function("latency").is_none());
+    }
+}
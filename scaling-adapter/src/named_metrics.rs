@@ -0,0 +1,116 @@
+use crate::errors::ConfigError;
+use crate::intervals::{IntervalData, IntervalDerivedData};
+
+/// calculates the values of all named metrics from the interval data
+pub type NamedMetricsCalc = Box<dyn Fn(&IntervalData) -> Vec<f64> + Send + Sync>;
+
+/// metric function that returns a variable set of named values per interval
+/// all values are stored in the history, one of them is used as scale metric
+/// and optionally one as reset metric
+pub struct NamedMetrics {
+    pub names: Vec<String>,
+    /// must return one value per name, in the same order
+    pub calc: NamedMetricsCalc,
+    /// name of the metric that is optimized
+    pub scale_metric: String,
+    /// name of the reset signal, None for a reset metric of 0
+    pub reset_metric: Option<String>,
+}
+
+impl NamedMetrics {
+    /// the first name is used as scale metric
+    /// panics if no names are given
+    pub fn new(names: &[&str], calc: NamedMetricsCalc) -> Self {
+        let scale_metric = names.first().expect("no metric names given").to_string();
+        NamedMetrics {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            calc,
+            scale_metric,
+            reset_metric: None,
+        }
+    }
+
+    /// panics if the name was not declared, see try_with_scale_metric
+    pub fn with_scale_metric(self, name: &str) -> Self {
+        self.try_with_scale_metric(name)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// panics if the name was not declared, see try_with_reset_metric
+    pub fn with_reset_metric(self, name: &str) -> Self {
+        self.try_with_reset_metric(name)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_scale_metric(mut self, name: &str) -> Result<Self, ConfigError> {
+        self.check_declared(name)?;
+        self.scale_metric = name.to_string();
+        Ok(self)
+    }
+
+    pub fn try_with_reset_metric(mut self, name: &str) -> Result<Self, ConfigError> {
+        self.check_declared(name)?;
+        self.reset_metric = Some(name.to_string());
+        Ok(self)
+    }
+
+    fn check_declared(&self, name: &str) -> Result<(), ConfigError> {
+        match self.index_of(name) {
+            Some(_) => Ok(()),
+            None => Err(ConfigError::UnknownMetric(name.to_string())),
+        }
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|known| known == name)
+    }
+
+    /// value of the named metric, None if unknown or missing in values
+    pub fn value_of(&self, values: &[f64], name: &str) -> Option<f64> {
+        self.index_of(name)
+            .and_then(|index| values.get(index))
+            .copied()
+    }
+
+    /// scale and reset metric taken from the named values
+    /// missing values are 0
+    pub fn derived_data(&self, values: &[f64]) -> IntervalDerivedData {
+        let reset_metric = match &self.reset_metric {
+            Some(name) => self.value_of(values, name),
+            None => None,
+        };
+        IntervalDerivedData {
+            scale_metric: self.value_of(values, &self.scale_metric).unwrap_or(0.0),
+            reset_metric: reset_metric.unwrap_or(0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_metrics() {
+        let metrics = NamedMetrics::new(
+            &["throughput", "blkio_delay", "fsync_count"],
+            Box::new(|_data| vec![1.0, 2.0, 3.0]),
+        );
+        let values = vec![10.0, 20.0, 30.0];
+        let derived_data = metrics.derived_data(&values);
+        assert_eq!(derived_data.scale_metric, 10.0);
+        assert_eq!(derived_data.reset_metric, 0.0);
+        let metrics = metrics
+            .with_scale_metric("fsync_count")
+            .with_reset_metric("blkio_delay");
+        let derived_data = metrics.derived_data(&values);
+        assert_eq!(derived_data.scale_metric, 30.0);
+        assert_eq!(derived_data.reset_metric, 20.0);
+        assert_eq!(metrics.value_of(&values, "unknown"), None);
+        assert_eq!(metrics.value_of(&values[..1], "blkio_delay"), None);
+        assert_eq!(
+            metrics.try_with_reset_metric("latency").err(),
+            Some(ConfigError::UnknownMetric(String::from("latency")))
+        );
+    }
+}
@@ -1,14 +1,95 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::ParameterConfig;
+use crate::errors::ConfigError;
+use crate::events::{AdviceObserver, LogObserver};
+use crate::expression::MetricExpression;
+use crate::filters::MetricFilter;
+use crate::guards::GuardMetric;
 use crate::intervals::{IntervalData, IntervalDerivedData};
+use crate::metrics;
+use crate::named_metrics::NamedMetrics;
+use crate::policies::ScalingPolicy;
+use crate::statistics;
 
 pub struct ScalingParameters {
     pub syscall_nrs: Vec<i32>,
     // calc_interval_metrics: fn(&IntervalData) -> IntervalMetrics,
     // allow closures, but restrict to thread-safe (implement Send, Sync)
     pub calc_metrics: Box<dyn Fn(&IntervalData) -> IntervalDerivedData + Send + Sync>,
+    /// identity of the scale metric of calc_metrics, None for custom functions
+    /// learned states are only restored for the same, known scale metric
+    pub scale_metric: Option<String>,
+    /// replaces calc_metrics if set, scale and reset metric are selected by name
+    pub named_metrics: Option<NamedMetrics>,
     /// minimum amount of time to pass before new interval starts
     pub check_interval_ms: u64,
     /// 0 < x < 1, margin of error when comparing scale metrics
     pub stability_factor: f64,
+    /// how the scale metrics of two pool sizes are compared
+    pub comparison: ComparisonMethod,
+    /// amount of valid intervals collected per pool size before comparing
+    pub samples_per_size: usize,
+    /// 0 < x < 1, p-value below which a statistical comparison is significant
+    pub significance_level: f64,
+    /// smoothing of the scale metric before it is used for decisions
+    pub metric_filter: MetricFilter,
+    /// 0 < x <= 1, factor by which older entries of the performance table decay per interval
+    pub aging_factor: f64,
+    /// jump directly to the best known pool size when the current one underperforms
+    pub jump_to_best: bool,
+    /// lower bound for the pool size, advice never shrinks the pool below it
+    pub min_size: usize,
+    /// upper bound for the pool size, advice never grows the pool above it, unbounded by default
+    pub max_size: usize,
+    /// metrics with ceilings, scale ups are rejected while a guard is violated
+    pub guards: Vec<GuardMetric>,
+    /// how long a pool size at which a guard was violated is not grown to again
+    pub guard_backoff_ms: u64,
+    /// valid intervals discarded after each scaling action, while new workers warm up
+    pub settling_intervals: usize,
+    /// minimum time after each scaling action before intervals are measured again
+    pub settling_ms: u64,
+    /// keep intervals during which targets changed instead of discarding them,
+    /// they are attributed to the time weighted average pool size
+    pub mixed_intervals: bool,
+    /// learned state restored at startup, see ScalingAdapter::save_state
+    pub state_file: Option<PathBuf>,
+    /// restored state older than this is ignored
+    pub max_state_age_ms: u64,
+    /// if the traceset cannot be created, run without kernel data instead of failing,
+    /// see ScalingAdapter::is_degraded
+    pub degraded_fallback: bool,
+    /// check every interval for implausible traceset data and freeze scaling while it shows up
+    pub watchdog: bool,
+    /// intervals longer than this multiple of check_interval_ms are implausible
+    pub watchdog_max_interval_factor: f64,
+    /// pool size to pin the pool to while scaling is frozen, None to keep the current size
+    pub watchdog_fallback_size: Option<usize>,
+    /// policy replacing the built-in state machine, None to use the state machine
+    pub policy: Option<Box<dyn ScalingPolicy>>,
+    /// time source of the adapter, replace with a ManualClock in tests and simulations
+    pub clock: Arc<dyn Clock>,
+    /// receive every scaling decision, by default only the LogObserver
+    pub observers: Vec<Box<dyn AdviceObserver>>,
+}
+
+/// how the adapter decides whether the latest pool size performs better or worse
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum ComparisonMethod {
+    /// compare the sample means, scaled by the stability factor
+    #[serde(rename = "factor")]
+    StabilityFactor,
+    /// one-sided Welch t-test on the sample means
+    #[serde(rename = "welch")]
+    WelchTTest,
+    /// one-sided Mann-Whitney U test on the sample ranks
+    #[serde(rename = "mannwhitney")]
+    MannWhitneyU,
 }
 
 impl Default for ScalingParameters {
@@ -16,20 +97,36 @@ impl Default for ScalingParameters {
         // read, write, fsync, openat, unlink (just use these for now)
         // remember: can trace max 8 syscalls
         let syscall_nrs = [0, 1, 74, 257, 87].to_vec();
-        let calc_metrics = Box::new(|data: &IntervalData| {
-            let rw_bytes = data.write_bytes + data.read_bytes;
-            let interval_ms = data.end_millis() - data.start_millis();
-            let throughput = rw_bytes as f64 / interval_ms as f64;
-            IntervalDerivedData {
-                scale_metric: throughput,
-                reset_metric: 0.0,
-            }
-        });
+        let calc_metrics = Box::new(metrics::read_write_bytes_per_ms);
         ScalingParameters {
             syscall_nrs,
             calc_metrics,
+            scale_metric: Some(String::from("throughput")),
+            named_metrics: None,
             check_interval_ms: 1000,
             stability_factor: 0.9,
+            comparison: ComparisonMethod::StabilityFactor,
+            samples_per_size: 1,
+            significance_level: 0.05,
+            metric_filter: MetricFilter::None,
+            aging_factor: 0.95,
+            jump_to_best: false,
+            min_size: 1,
+            max_size: usize::MAX,
+            guards: Vec::new(),
+            guard_backoff_ms: 10000,
+            settling_intervals: 0,
+            settling_ms: 0,
+            mixed_intervals: false,
+            state_file: None,
+            max_state_age_ms: 7 * 24 * 3600 * 1000,
+            degraded_fallback: false,
+            watchdog: false,
+            watchdog_max_interval_factor: 5.0,
+            watchdog_fallback_size: None,
+            policy: None,
+            clock: Arc::new(SystemClock),
+            observers: vec![Box::new(LogObserver)],
         }
     }
 }
@@ -39,33 +136,171 @@ impl ScalingParameters {
         syscall_nrs: Vec<i32>,
         calc_metrics: Box<dyn Fn(&IntervalData) -> IntervalDerivedData + Send + Sync>,
     ) -> Self {
-        let default_check_interval_ms = 1000;
-        let default_stability_factor = 0.9;
         ScalingParameters {
             syscall_nrs,
             calc_metrics,
-            check_interval_ms: default_check_interval_ms,
-            stability_factor: default_stability_factor,
+            scale_metric: None,
+            ..Default::default()
         }
     }
 
+    /// parameters of a shipped preset, see config::PRESETS
+    pub fn preset(name: &str) -> Result<Self, ConfigError> {
+        ParameterConfig::preset(name)?.to_parameters()
+    }
+
+    /// load parameters from a toml or yaml config file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        ParameterConfig::from_file(path)?.to_parameters()
+    }
+
     /// take params separated as string "<param1>,<param2>"
     /// same order as in struct
-    pub fn with_algo_params(mut self, params_untyped: &str) -> Self {
-        let param_strs = params_untyped.split(',').collect::<Vec<&str>>();
-        let check_interval_ms: u64 = param_strs
-            .get(0)
-            .expect("malformatted params string")
-            .parse()
-            .expect("invalid check interval ms parameter");
-        let stability_factor: f64 = param_strs
-            .get(1)
-            .expect("malformatted params string")
-            .parse()
-            .expect("invalid stability factor parameter");
-        self.check_interval_ms = check_interval_ms;
-        self.stability_factor = stability_factor;
-        self
+    /// optionally followed by "<comparison>,<samples_per_size>,<significance_level>"
+    /// where comparison is one of "factor", "welch", "mannwhitney"
+    ///
+    /// will panic for invalid params, see try_with_algo_params
+    #[deprecated(note = "panics for invalid params, use try_with_algo_params")]
+    pub fn with_algo_params(self, params_untyped: &str) -> Self {
+        self.try_with_algo_params(params_untyped)
+            .unwrap_or_else(|e| panic!("invalid algo params \"{}\": {}", params_untyped, e))
+    }
+
+    /// same as with_algo_params, but returns an error for invalid params
+    pub fn try_with_algo_params(mut self, params_untyped: &str) -> Result<Self, ConfigError> {
+        let mut param_strs = params_untyped.split(',');
+        let mut next_param = |parameter: &'static str| {
+            param_strs.next().ok_or(ConfigError::InvalidValue {
+                parameter,
+                reason: String::from("missing"),
+            })
+        };
+        self.check_interval_ms =
+            parse_param("check_interval_ms", next_param("check_interval_ms")?)?;
+        self.stability_factor = parse_param("stability_factor", next_param("stability_factor")?)?;
+        if let Ok(comparison) = next_param("comparison") {
+            self.comparison = match comparison {
+                "factor" => ComparisonMethod::StabilityFactor,
+                "welch" => ComparisonMethod::WelchTTest,
+                "mannwhitney" => ComparisonMethod::MannWhitneyU,
+                _ => {
+                    return Err(ConfigError::InvalidValue {
+                        parameter: "comparison",
+                        reason: format!("unknown comparison method {}", comparison),
+                    })
+                }
+            };
+            self.samples_per_size =
+                parse_param("samples_per_size", next_param("samples_per_size")?)?;
+            if let Ok(significance_level) = next_param("significance_level") {
+                self.significance_level = parse_param("significance_level", significance_level)?;
+            }
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// check that all parameters are within their allowed range
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |parameter: &'static str, reason: &str| {
+            Err(ConfigError::InvalidValue {
+                parameter,
+                reason: reason.to_string(),
+            })
+        };
+        if self.syscall_nrs.len() > 8 {
+            return invalid("syscalls", "at most 8 syscalls can be traced");
+        }
+        if self.check_interval_ms == 0 {
+            return invalid("check_interval_ms", "must be > 0");
+        }
+        if !(self.stability_factor > 0.0 && self.stability_factor < 1.0) {
+            return invalid("stability_factor", "must be > 0 and < 1");
+        }
+        if self.samples_per_size == 0 {
+            return invalid("samples_per_size", "must be >= 1");
+        }
+        if self.comparison != ComparisonMethod::StabilityFactor && self.samples_per_size < 2 {
+            return invalid(
+                "samples_per_size",
+                "statistical tests need at least 2 samples",
+            );
+        }
+        if !(self.significance_level > 0.0 && self.significance_level < 1.0) {
+            return invalid("significance_level", "must be > 0 and < 1");
+        }
+        if self.comparison == ComparisonMethod::MannWhitneyU
+            && statistics::mann_whitney_u_min_p_value(self.samples_per_size)
+                >= self.significance_level
+        {
+            return invalid(
+                "samples_per_size",
+                "too few samples for the Mann-Whitney U test to reach the significance level",
+            );
+        }
+        if !(self.aging_factor > 0.0 && self.aging_factor <= 1.0) {
+            return invalid("aging_factor", "must be > 0 and <= 1");
+        }
+        if self.min_size == 0 || self.min_size > self.max_size {
+            return invalid("min_size", "must be >= 1 and <= max_size");
+        }
+        let max_interval_factor = self.watchdog_max_interval_factor;
+        if !(max_interval_factor > 1.0 && max_interval_factor.is_finite()) {
+            return invalid("watchdog_max_interval_factor", "must be > 1 and finite");
+        }
+        if self.watchdog_fallback_size == Some(0) {
+            return invalid("watchdog_fallback_size", "must be >= 1");
+        }
+        // guards on named metrics would never fire if the name is not declared
+        for guard in self.guards.iter().filter(|guard| guard.calc.is_none()) {
+            if !matches!(&self.named_metrics, Some(named) if named.index_of(&guard.name).is_some())
+            {
+                return Err(ConfigError::UnknownMetric(guard.name.clone()));
+            }
+        }
+        match self.metric_filter {
+            MetricFilter::Ewma { alpha } if !(alpha > 0.0 && alpha <= 1.0) => {
+                invalid("metric_filter", "alpha must be > 0 and <= 1")
+            }
+            MetricFilter::MovingMedian { window }
+            | MetricFilter::OutlierRejection { window, .. }
+                if window == 0 =>
+            {
+                invalid("metric_filter", "window must be >= 1")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// use expressions over the interval fields as scale and reset metric
+    /// without reset expression the reset metric is the scale metric
+    /// syscalls in the expressions must be traced, so call after setting syscall_nrs
+    pub fn with_metric_expression(
+        mut self,
+        scale_expression: &str,
+        reset_expression: Option<&str>,
+    ) -> Result<Self, ConfigError> {
+        let scale = MetricExpression::compile(scale_expression, &self.syscall_nrs)?;
+        let reset = match reset_expression {
+            Some(reset_expression) => {
+                MetricExpression::compile(reset_expression, &self.syscall_nrs)?
+            }
+            None => scale.clone(),
+        };
+        self.scale_metric = Some(format!("expression:{}", scale.source()));
+        self.calc_metrics = Box::new(move |data: &IntervalData| IntervalDerivedData {
+            scale_metric: scale.evaluate(data),
+            reset_metric: reset.evaluate(data),
+        });
+        Ok(self)
+    }
+
+    /// use a metric function of the catalog, see metrics::METRICS
+    pub fn with_catalog_metric(mut self, name: &str) -> Result<Self, ConfigError> {
+        self.calc_metrics = metrics::metric_function(name)
+            .ok_or_else(|| ConfigError::UnknownMetric(name.to_string()))?;
+        self.scale_metric = Some(name.to_string());
+        Ok(self)
     }
 
     pub fn with_check_interval_ms(mut self, check_interval_ms: u64) -> Self {
@@ -78,4 +313,131 @@ impl ScalingParameters {
         self.stability_factor = stability_factor;
         self
     }
-}
\ No newline at end of file
+
+    /// compare pool sizes by collecting samples_per_size intervals for each size
+    /// significance level is only used by the statistical tests
+    /// statistical tests need at least 2 samples per size (Mann-Whitney U at least 3),
+    /// fewer are raised to that minimum
+    pub fn with_comparison(
+        mut self,
+        comparison: ComparisonMethod,
+        samples_per_size: usize,
+        significance_level: f64,
+    ) -> Self {
+        let min_samples = match comparison {
+            ComparisonMethod::StabilityFactor => 1,
+            ComparisonMethod::WelchTTest => 2,
+            // with 2 samples the test can never be significant at 0.05
+            ComparisonMethod::MannWhitneyU => 3,
+        };
+        self.comparison = comparison;
+        self.samples_per_size = samples_per_size.max(min_samples);
+        self.significance_level = significance_level;
+        self
+    }
+
+    pub fn with_metric_filter(mut self, metric_filter: MetricFilter) -> Self {
+        self.metric_filter = metric_filter;
+        self
+    }
+
+    /// aging factor should be > 0 and <= 1
+    /// jump_to_best: move to best known pool size instead of stepping back
+    pub fn with_performance_table(mut self, aging_factor: f64, jump_to_best: bool) -> Self {
+        self.aging_factor = aging_factor;
+        self.jump_to_best = jump_to_best;
+        self
+    }
+
+    /// min_size should be >= 1 and <= max_size
+    pub fn with_bounds(mut self, min_size: usize, max_size: usize) -> Self {
+        self.min_size = min_size;
+        self.max_size = max_size;
+        self
+    }
+
+    /// use a metric function returning named values instead of calc_metrics
+    pub fn with_named_metrics(mut self, named_metrics: NamedMetrics) -> Self {
+        self.named_metrics = Some(named_metrics);
+        self
+    }
+
+    /// add a guard metric, can be called multiple times
+    pub fn with_guard(mut self, guard: GuardMetric) -> Self {
+        self.guards.push(guard);
+        self
+    }
+
+    pub fn with_guard_backoff_ms(mut self, guard_backoff_ms: u64) -> Self {
+        self.guard_backoff_ms = guard_backoff_ms;
+        self
+    }
+
+    /// after each scaling action, discard at least settling_intervals valid intervals
+    /// and all intervals starting within settling_ms
+    pub fn with_settling(mut self, settling_intervals: usize, settling_ms: u64) -> Self {
+        self.settling_intervals = settling_intervals;
+        self.settling_ms = settling_ms;
+        self
+    }
+
+    /// keep intervals spanning a change of the targets, weighted by the time at each pool size
+    pub fn with_mixed_intervals(mut self, mixed_intervals: bool) -> Self {
+        self.mixed_intervals = mixed_intervals;
+        self
+    }
+
+    /// warm start: restore the state learned by an earlier run from state_file,
+    /// unless it is older than max_state_age_ms or was learned with other parameters
+    pub fn with_state_file(
+        mut self,
+        state_file: impl Into<PathBuf>,
+        max_state_age_ms: u64,
+    ) -> Self {
+        self.state_file = Some(state_file.into());
+        self.max_state_age_ms = max_state_age_ms;
+        self
+    }
+
+    /// on kernels without traceset support, scale by completed jobs and queue length
+    /// instead of failing the creation of the adapter
+    pub fn with_degraded_fallback(mut self, degraded_fallback: bool) -> Self {
+        self.degraded_fallback = degraded_fallback;
+        self
+    }
+
+    /// freeze scaling while the traceset counters are implausible (see HealthIssue),
+    /// optionally pinning the pool to fallback_size meanwhile
+    pub fn with_watchdog(mut self, max_interval_factor: f64, fallback_size: Option<usize>) -> Self {
+        self.watchdog = true;
+        self.watchdog_max_interval_factor = max_interval_factor;
+        self.watchdog_fallback_size = fallback_size;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: Box<dyn ScalingPolicy>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// add an observer, keeps the existing ones (including the LogObserver)
+    pub fn with_observer(mut self, observer: Box<dyn AdviceObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+}
+
+fn parse_param<T: std::str::FromStr>(
+    parameter: &'static str,
+    value: &str,
+) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue {
+        parameter,
+        reason: format!("could not parse {}", value),
+    })
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+/// entries with a lower weight are not considered when looking for the best pool size
+const MIN_KNOWN_WEIGHT: f64 = 0.5;
+/// entries with a lower weight are removed from the table
+const MIN_KEPT_WEIGHT: f64 = 0.01;
+
+/// aggregated scale metric observations of one pool size
+#[derive(Clone, Copy, Debug)]
+pub struct SizeStatistics {
+    /// weighted mean of the observed (filtered) scale metric
+    pub mean: f64,
+    /// sum of the aged observation weights, every observation starts with weight 1
+    pub weight: f64,
+    /// total amount of observations, not affected by aging
+    pub observations: usize,
+}
+
+/// remembers the observed scale metric per pool size
+/// with every new observation the weight of all entries is multiplied by the aging factor,
+/// so entries of pool sizes that have not been observed for a while become stale
+pub struct PerformanceTable {
+    entries: BTreeMap<usize, SizeStatistics>,
+    aging_factor: f64,
+}
+
+impl PerformanceTable {
+    /// aging factor should be > 0 and <= 1 (1 disables aging)
+    pub fn new(aging_factor: f64) -> Self {
+        PerformanceTable {
+            entries: BTreeMap::new(),
+            aging_factor,
+        }
+    }
+
+    /// age all entries, then add the observation for the given pool size
+    pub fn add(&mut self, pool_size: usize, metric: f64) {
+        self.add_weighted(pool_size, metric, 1.0);
+    }
+
+    /// same as add, but the observation starts with the given weight (0 < weight <= 1)
+    pub fn add_weighted(&mut self, pool_size: usize, metric: f64, weight: f64) {
+        let aging_factor = self.aging_factor;
+        for statistics in self.entries.values_mut() {
+            statistics.weight *= aging_factor;
+        }
+        self.entries
+            .retain(|_size, statistics| statistics.weight >= MIN_KEPT_WEIGHT);
+        let statistics = self.entries.entry(pool_size).or_insert(SizeStatistics {
+            mean: 0.0,
+            weight: 0.0,
+            observations: 0,
+        });
+        let new_weight = statistics.weight + weight;
+        statistics.mean = (statistics.mean * statistics.weight + metric * weight) / new_weight;
+        statistics.weight = new_weight;
+        statistics.observations += 1;
+    }
+
+    /// set the statistics of a pool size without aging, e.g. restored from an earlier run
+    pub fn insert_prior(&mut self, pool_size: usize, statistics: SizeStatistics) {
+        self.entries.insert(pool_size, statistics);
+    }
+
+    pub fn get(&self, pool_size: usize) -> Option<&SizeStatistics> {
+        self.entries.get(&pool_size)
+    }
+
+    /// all entries ordered by pool size
+    pub fn entries(&self) -> impl Iterator<Item = (usize, &SizeStatistics)> {
+        self.entries
+            .iter()
+            .map(|(size, statistics)| (*size, statistics))
+    }
+
+    /// pool size with the highest mean among entries that are not stale
+    pub fn best(&self) -> Option<(usize, &SizeStatistics)> {
+        self.entries()
+            .filter(|(_size, statistics)| statistics.weight >= MIN_KNOWN_WEIGHT)
+            .max_by(|(_, a), (_, b)| {
+                a.mean
+                    .partial_cmp(&b.mean)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn performance_table() {
+        let mut table = PerformanceTable::new(0.9);
+        table.add(1, 10.0);
+        table.add(2, 20.0);
+        table.add(2, 22.0);
+        table.add(3, 15.0);
+        assert_eq!(table.best().unwrap().0, 2);
+        let size_2 = table.get(2).unwrap();
+        assert_eq!(size_2.observations, 2);
+        assert!(size_2.mean > 21.0 && size_2.mean < 22.0);
+        // size 2 becomes stale when only size 3 is observed
+        for _ in 0..50 {
+            table.add(3, 15.0);
+        }
+        assert_eq!(table.best().unwrap().0, 3);
+        assert!(table.get(1).is_none());
+    }
+}
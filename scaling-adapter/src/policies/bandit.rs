@@ -0,0 +1,267 @@
+use std::any::Any;
+
+use log::{debug, info};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{PolicyContext, ScalingPolicy};
+
+/// how the bandit policy picks the next arm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BanditAlgorithm {
+    /// upper confidence bound, exploration scales the confidence term (1.0 for classic UCB1)
+    Ucb1 { exploration: f64 },
+    /// gaussian Thompson sampling over the normalized arm means
+    ThompsonSampling,
+}
+
+/// current knowledge about one candidate pool size
+#[derive(Clone, Copy, Debug)]
+pub struct ArmEstimate {
+    pub size: usize,
+    /// discounted amount of rewards received
+    pub count: f64,
+    /// discounted mean of the scale metric
+    pub mean: f64,
+    /// mean normalized by the best arm mean, between 0 and 1
+    pub normalized_mean: f64,
+}
+
+/// treats a set of candidate pool sizes as arms of a multi-armed bandit
+/// every arm is held for hold_intervals valid intervals, the mean scale metric is its reward
+/// with a discount factor < 1 old rewards fade, so a drifting optimum is picked up again
+pub struct BanditPolicy {
+    algorithm: BanditAlgorithm,
+    hold_intervals: usize,
+    discount: f64,
+    sizes: Vec<usize>,
+    counts: Vec<f64>,
+    sums: Vec<f64>,
+    rng: StdRng,
+}
+
+impl BanditPolicy {
+    /// sizes: candidate pool sizes, discount: 0 < x <= 1 (1 for a stationary workload)
+    pub fn new(
+        algorithm: BanditAlgorithm,
+        sizes: Vec<usize>,
+        hold_intervals: usize,
+        discount: f64,
+    ) -> Self {
+        BanditPolicy::with_rng(
+            algorithm,
+            sizes,
+            hold_intervals,
+            discount,
+            StdRng::from_entropy(),
+        )
+    }
+
+    /// same as new, but with a seeded random number generator for reproducible runs
+    pub fn with_seed(
+        algorithm: BanditAlgorithm,
+        sizes: Vec<usize>,
+        hold_intervals: usize,
+        discount: f64,
+        seed: u64,
+    ) -> Self {
+        BanditPolicy::with_rng(
+            algorithm,
+            sizes,
+            hold_intervals,
+            discount,
+            StdRng::seed_from_u64(seed),
+        )
+    }
+
+    fn with_rng(
+        algorithm: BanditAlgorithm,
+        mut sizes: Vec<usize>,
+        hold_intervals: usize,
+        discount: f64,
+        rng: StdRng,
+    ) -> Self {
+        sizes.sort_unstable();
+        sizes.dedup();
+        let amount_arms = sizes.len();
+        BanditPolicy {
+            algorithm,
+            hold_intervals: hold_intervals.max(1),
+            discount,
+            sizes,
+            counts: vec![0.0; amount_arms],
+            sums: vec![0.0; amount_arms],
+            rng,
+        }
+    }
+
+    pub fn arm_estimates(&self) -> Vec<ArmEstimate> {
+        let means: Vec<f64> = self
+            .counts
+            .iter()
+            .zip(self.sums.iter())
+            .map(|(count, sum)| if *count > 0.0 { sum / count } else { 0.0 })
+            .collect();
+        let best_mean = means.iter().cloned().fold(0.0, f64::max);
+        self.sizes
+            .iter()
+            .zip(self.counts.iter())
+            .zip(means.iter())
+            .map(|((size, count), mean)| ArmEstimate {
+                size: *size,
+                count: *count,
+                mean: *mean,
+                normalized_mean: if best_mean > 0.0 {
+                    mean / best_mean
+                } else {
+                    0.0
+                },
+            })
+            .collect()
+    }
+
+    /// discount all arms, then add the reward to the given arm
+    fn add_reward(&mut self, arm: usize, reward: f64) {
+        for (count, sum) in self.counts.iter_mut().zip(self.sums.iter_mut()) {
+            *count *= self.discount;
+            *sum *= self.discount;
+        }
+        self.counts[arm] += 1.0;
+        self.sums[arm] += reward;
+    }
+
+    fn select_arm(&mut self) -> Option<usize> {
+        // every arm is played once before any estimate is trusted
+        if let Some(unplayed) = self.counts.iter().position(|count| *count <= 0.0) {
+            return Some(unplayed);
+        }
+        let estimates = self.arm_estimates();
+        let total_count: f64 = self.counts.iter().sum();
+        let scores: Vec<f64> = match self.algorithm {
+            BanditAlgorithm::Ucb1 { exploration } => estimates
+                .iter()
+                .map(|arm| {
+                    arm.normalized_mean
+                        + exploration * (2.0 * total_count.max(1.0).ln() / arm.count).sqrt()
+                })
+                .collect(),
+            BanditAlgorithm::ThompsonSampling => {
+                let rng = &mut self.rng;
+                estimates
+                    .iter()
+                    .map(|arm| {
+                        let std_deviation = (1.0 / (arm.count + 1.0)).sqrt();
+                        arm.normalized_mean + std_deviation * standard_normal(rng)
+                    })
+                    .collect()
+            }
+        };
+        (0..scores.len()).max_by(|a, b| {
+            scores[*a]
+                .partial_cmp(&scores[*b])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+}
+
+/// sample of the standard normal distribution (Box-Muller transform)
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON, 1.0);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+impl ScalingPolicy for BanditPolicy {
+    fn scaling_advice(&mut self, context: &PolicyContext) -> i32 {
+        let current_size = context.amount_targets;
+        if context.intervals_at_current_size() < self.hold_intervals {
+            debug!("BANDIT: holding pool size {}", current_size);
+            return 0;
+        }
+        // pool sizes that are not an arm do not give a reward, the pool is moved to an arm
+        if let Some(arm) = self.sizes.iter().position(|size| *size == current_size) {
+            let reward = context
+                .history
+                .iter()
+                .take(self.hold_intervals)
+                .map(|metrics| metrics.filtered_scale_metric)
+                .sum::<f64>()
+                / self.hold_intervals as f64;
+            self.add_reward(arm, reward);
+        }
+        let target_size = match self.select_arm() {
+            Some(arm) => self.sizes[arm],
+            None => current_size,
+        };
+        info!("BANDIT: estimates: {:?}", self.arm_estimates());
+        target_size as i32 - current_size as i32
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intervals::IntervalMetrics;
+    use crate::performance::PerformanceTable;
+    use std::time::Instant;
+
+    fn run(policy: &mut BanditPolicy, curve: &dyn Fn(usize) -> f64, size: usize) -> usize {
+        let performance = PerformanceTable::new(1.0);
+        let history = &[IntervalMetrics::for_test(size, curve(size))];
+        let context = PolicyContext {
+            history: history.iter().collect(),
+            performance: &performance,
+            amount_targets: size,
+            min_size: 1,
+            max_size: 64,
+            queue_size: 0,
+            metric_names: &[],
+            now: Instant::now(),
+        };
+        (size as i32 + policy.scaling_advice(&context)) as usize
+    }
+
+    fn most_played(policy: &BanditPolicy) -> usize {
+        policy
+            .arm_estimates()
+            .iter()
+            .max_by(|a, b| a.count.partial_cmp(&b.count).unwrap())
+            .unwrap()
+            .size
+    }
+
+    #[test]
+    fn ucb_prefers_best_arm() {
+        let curve = |size: usize| [0.0, 10.0, 18.0, 0.0, 25.0, 0.0, 0.0, 0.0, 20.0][size];
+        let algorithm = BanditAlgorithm::Ucb1 { exploration: 0.5 };
+        let mut policy = BanditPolicy::with_seed(algorithm, vec![1, 2, 4, 8], 1, 1.0, 0);
+        let mut size = 1;
+        for _ in 0..100 {
+            size = run(&mut policy, &curve, size);
+        }
+        assert_eq!(most_played(&policy), 4);
+        let estimates = policy.arm_estimates();
+        assert!((estimates[2].mean - 25.0).abs() < 1e-9);
+        assert!((estimates[2].normalized_mean - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn thompson_follows_drifting_optimum() {
+        let algorithm = BanditAlgorithm::ThompsonSampling;
+        let mut policy = BanditPolicy::with_seed(algorithm, vec![1, 2, 4, 8], 1, 0.9, 42);
+        let mut size = 1;
+        let early = |size: usize| if size == 2 { 30.0 } else { 10.0 };
+        for _ in 0..100 {
+            size = run(&mut policy, &early, size);
+        }
+        assert_eq!(most_played(&policy), 2);
+        let late = |size: usize| if size == 8 { 30.0 } else { 10.0 };
+        for _ in 0..100 {
+            size = run(&mut policy, &late, size);
+        }
+        assert_eq!(most_played(&policy), 8);
+    }
+}
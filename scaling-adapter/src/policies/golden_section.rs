@@ -0,0 +1,221 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+
+use log::{debug, info};
+
+use super::{PolicyContext, ScalingPolicy, UNBOUNDED_SEARCH_LIMIT};
+
+/// 1 / golden ratio
+const INVERSE_PHI: f64 = 0.618_033_988_749_895;
+
+enum SearchPhase {
+    /// search not started yet, bounds are taken from the first context
+    Idle,
+    /// the maximum lies within [lower, upper]
+    Searching {
+        lower: usize,
+        upper: usize,
+        /// mean scale metric of every probed pool size in this search
+        measured: BTreeMap<usize, f64>,
+    },
+    /// search converged, the metric measured at the end of the search is kept as reference
+    Settled {
+        size: usize,
+        reference: f64,
+        intervals: usize,
+    },
+}
+
+/// bounded golden-section search for the pool size with the highest scale metric
+/// assumes the scale metric is unimodal in the pool size
+/// every probed pool size is held for hold_intervals valid intervals,
+/// after convergence the settled pool size is re-validated every revalidation_intervals
+/// and the search restarts if the metric moved away more than tolerance (relative)
+pub struct GoldenSectionPolicy {
+    hold_intervals: usize,
+    revalidation_intervals: usize,
+    tolerance: f64,
+    phase: SearchPhase,
+}
+
+impl GoldenSectionPolicy {
+    pub fn new(hold_intervals: usize, revalidation_intervals: usize, tolerance: f64) -> Self {
+        GoldenSectionPolicy {
+            hold_intervals: hold_intervals.max(1),
+            revalidation_intervals: revalidation_intervals.max(1),
+            tolerance,
+            phase: SearchPhase::Idle,
+        }
+    }
+
+    /// pool size the search converged to, None while searching
+    pub fn settled_size(&self) -> Option<usize> {
+        match self.phase {
+            SearchPhase::Settled { size, .. } => Some(size),
+            _ => None,
+        }
+    }
+
+    /// next pool size that has to be measured, or None if the search converged
+    fn next_probe(
+        lower: &mut usize,
+        upper: &mut usize,
+        measured: &BTreeMap<usize, f64>,
+    ) -> Option<usize> {
+        loop {
+            // small intervals are searched exhaustively
+            if *upper - *lower <= 2 {
+                return (*lower..=*upper).find(|size| !measured.contains_key(size));
+            }
+            let step = (INVERSE_PHI * (*upper - *lower) as f64).round() as usize;
+            let left = *upper - step;
+            let right = (*lower + step).max(left + 1);
+            let (left_metric, right_metric) = match (measured.get(&left), measured.get(&right)) {
+                (Some(left_metric), Some(right_metric)) => (left_metric, right_metric),
+                (None, _) => return Some(left),
+                (_, None) => return Some(right),
+            };
+            // narrow the interval towards the better probe
+            if left_metric < right_metric {
+                *lower = left;
+            } else {
+                *upper = right;
+            }
+        }
+    }
+}
+
+/// mean of the filtered scale metric of the latest intervals at the current pool size
+fn current_mean(context: &PolicyContext, amount: usize) -> f64 {
+    let values: Vec<f64> = context
+        .history
+        .iter()
+        .take(amount)
+        .map(|metrics| metrics.filtered_scale_metric)
+        .collect();
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+impl ScalingPolicy for GoldenSectionPolicy {
+    fn scaling_advice(&mut self, context: &PolicyContext) -> i32 {
+        let current_size = context.amount_targets;
+        if context.intervals_at_current_size() < self.hold_intervals {
+            debug!("GOLDEN: holding pool size {}", current_size);
+            return 0;
+        }
+        let metric = current_mean(context, self.hold_intervals);
+        if let SearchPhase::Settled {
+            size,
+            reference,
+            intervals,
+        } = &mut self.phase
+        {
+            // pool did not follow the advice (yet)
+            if current_size != *size {
+                return *size as i32 - current_size as i32;
+            }
+            *intervals += 1;
+            if *intervals < self.revalidation_intervals {
+                return 0;
+            }
+            *intervals = 0;
+            let deviation = (metric - *reference).abs() / reference.abs().max(f64::EPSILON);
+            if deviation <= self.tolerance {
+                debug!("GOLDEN: settled pool size {} still valid", size);
+                return 0;
+            }
+            info!(
+                "GOLDEN: metric deviates {} from reference at pool size {}, restart search",
+                deviation, size
+            );
+            self.phase = SearchPhase::Idle;
+        }
+        if let SearchPhase::Idle = self.phase {
+            let max_size = if context.max_size == usize::MAX {
+                UNBOUNDED_SEARCH_LIMIT
+            } else {
+                context.max_size
+            };
+            self.phase = SearchPhase::Searching {
+                lower: context.min_size,
+                upper: max_size.max(context.min_size),
+                measured: BTreeMap::new(),
+            };
+        }
+        let target_size = match &mut self.phase {
+            SearchPhase::Searching {
+                lower,
+                upper,
+                measured,
+            } => {
+                measured.insert(current_size, metric);
+                match GoldenSectionPolicy::next_probe(lower, upper, measured) {
+                    Some(probe) => {
+                        debug!("GOLDEN: searching [{}, {}], probe {}", lower, upper, probe);
+                        probe
+                    }
+                    None => {
+                        // best measured pool size within the final interval
+                        let (best_size, best_metric) = measured
+                            .range(*lower..=*upper)
+                            .max_by(|(_, a), (_, b)| {
+                                a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+                            })
+                            .map(|(size, metric)| (*size, *metric))
+                            .unwrap_or((current_size, metric));
+                        info!("GOLDEN: search converged to pool size {}", best_size);
+                        self.phase = SearchPhase::Settled {
+                            size: best_size,
+                            reference: best_metric,
+                            intervals: 0,
+                        };
+                        best_size
+                    }
+                }
+            }
+            _ => current_size,
+        };
+        target_size as i32 - current_size as i32
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intervals::IntervalMetrics;
+    use crate::performance::PerformanceTable;
+    use std::time::Instant;
+
+    #[test]
+    fn converges_to_maximum() {
+        // unimodal curve with maximum at 11 threads
+        let curve = |size: usize| 100.0 - (size as f64 - 11.0).powi(2);
+        let performance = PerformanceTable::new(1.0);
+        let mut policy = GoldenSectionPolicy::new(2, 5, 0.1);
+        let mut size = 1;
+        let mut decisions = 0;
+        while policy.settled_size().is_none() {
+            let latest = IntervalMetrics::for_test(size, curve(size));
+            let history = [IntervalMetrics::for_test(size, curve(size)), latest];
+            let context = PolicyContext {
+                history: history.iter().collect(),
+                performance: &performance,
+                amount_targets: size,
+                min_size: 1,
+                max_size: 32,
+                queue_size: 0,
+                metric_names: &[],
+                now: Instant::now(),
+            };
+            size = (size as i32 + policy.scaling_advice(&context)) as usize;
+            decisions += 1;
+            assert!(decisions < 20, "search did not converge");
+        }
+        assert_eq!(size, 11);
+        assert_eq!(policy.settled_size(), Some(11));
+    }
+}
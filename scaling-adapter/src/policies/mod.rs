@@ -0,0 +1,63 @@
+use std::any::Any;
+use std::time::Instant;
+
+use crate::intervals::IntervalMetrics;
+use crate::performance::PerformanceTable;
+
+pub use bandit::{ArmEstimate, BanditAlgorithm, BanditPolicy};
+pub use golden_section::GoldenSectionPolicy;
+pub use pid::{MetricResponse, PidGains, PidPolicy, TrackedMetric};
+pub use usl::{UslModel, UslPolicy};
+
+mod bandit;
+mod golden_section;
+mod pid;
+mod usl;
+
+/// upper end of the searched pool sizes if the parameters do not bound the pool size
+const UNBOUNDED_SEARCH_LIMIT: usize = 64;
+
+/// observations available to a scaling policy when it is asked for advice
+pub struct PolicyContext<'a> {
+    /// valid intervals, from newest to oldest
+    pub history: Vec<&'a IntervalMetrics>,
+    /// observed scale metric statistics per pool size
+    pub performance: &'a PerformanceTable,
+    /// amount of targets at the end of the latest interval
+    pub amount_targets: usize,
+    pub min_size: usize,
+    pub max_size: usize,
+    /// queue size passed to get_scaling_advice
+    pub queue_size: i32,
+    /// names of the named metrics, same order as the values in the intervals
+    pub metric_names: &'a [String],
+    /// monotonic time of the decision
+    pub now: Instant,
+}
+
+impl<'a> PolicyContext<'a> {
+    /// amount of consecutive latest intervals that were measured with the current amount of targets
+    pub fn intervals_at_current_size(&self) -> usize {
+        self.history
+            .iter()
+            .take_while(|metrics| metrics.amount_targets == self.amount_targets)
+            .count()
+    }
+
+    /// value of the named metric in the latest interval
+    pub fn latest_named_value(&self, name: &str) -> Option<f64> {
+        let index = self.metric_names.iter().position(|known| known == name)?;
+        self.history.first()?.named_values.get(index).copied()
+    }
+}
+
+/// replaces the built-in state machine of the adapter
+/// a policy is asked for advice once per valid interval
+pub trait ScalingPolicy: Send + Sync {
+    /// return by how many targets the pool should grow (> 0) or shrink (< 0)
+    /// the adapter restricts the advice to the configured pool size bounds
+    fn scaling_advice(&mut self, context: &PolicyContext) -> i32;
+
+    /// allows the adapter to hand out the concrete policy for inspection
+    fn as_any(&self) -> &dyn Any;
+}
@@ -0,0 +1,218 @@
+use std::any::Any;
+
+use log::{debug, info};
+
+use super::{PolicyContext, ScalingPolicy};
+
+/// metric the PID controller holds at the setpoint
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrackedMetric {
+    /// filtered scale metric of the latest interval
+    ScaleMetric,
+    /// reset metric of the latest interval
+    ResetMetric,
+    /// queue size passed to get_scaling_advice
+    QueueSize,
+    /// named metric of the latest interval
+    Named(String),
+}
+
+/// how the tracked metric reacts to a larger pool
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricResponse {
+    /// e.g. blkio delay per interval
+    IncreasesWithSize,
+    /// e.g. queue length
+    DecreasesWithSize,
+}
+
+/// gains of the controller, the unit of the output is pool size
+/// the integral and derivative terms are computed per decision (valid interval)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PidGains {
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+}
+
+/// PID controller that holds a metric at a setpoint by changing the pool size
+/// the controller output is the target pool size relative to the pool size at the first decision
+/// anti-windup: the integral does not grow while the output is saturated at the pool size bounds
+/// rate limiting: the pool size changes by at most max_step per decision
+pub struct PidPolicy {
+    metric: TrackedMetric,
+    setpoint: f64,
+    response: MetricResponse,
+    gains: PidGains,
+    max_step: usize,
+    /// pool size at the first decision, the output is added to it
+    base_size: Option<f64>,
+    integral: f64,
+    previous_error: Option<f64>,
+}
+
+impl PidPolicy {
+    pub fn new(
+        metric: TrackedMetric,
+        setpoint: f64,
+        response: MetricResponse,
+        gains: PidGains,
+    ) -> Self {
+        PidPolicy {
+            metric,
+            setpoint,
+            response,
+            gains,
+            max_step: usize::MAX,
+            base_size: None,
+            integral: 0.0,
+            previous_error: None,
+        }
+    }
+
+    /// limit the change of the pool size per decision
+    pub fn with_max_step(mut self, max_step: usize) -> Self {
+        self.max_step = max_step.max(1);
+        self
+    }
+
+    pub fn setpoint(&self) -> f64 {
+        self.setpoint
+    }
+
+    pub fn set_setpoint(&mut self, setpoint: f64) {
+        self.setpoint = setpoint;
+    }
+
+    /// accumulated error (already scaled with ki)
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    fn current_value(&self, context: &PolicyContext) -> Option<f64> {
+        match &self.metric {
+            TrackedMetric::ScaleMetric => context
+                .history
+                .first()
+                .map(|metrics| metrics.filtered_scale_metric),
+            TrackedMetric::ResetMetric => context
+                .history
+                .first()
+                .map(|metrics| metrics.derived_data.reset_metric),
+            TrackedMetric::QueueSize => Some(context.queue_size as f64),
+            TrackedMetric::Named(name) => context.latest_named_value(name),
+        }
+    }
+}
+
+impl ScalingPolicy for PidPolicy {
+    fn scaling_advice(&mut self, context: &PolicyContext) -> i32 {
+        let value = match self.current_value(context) {
+            Some(value) => value,
+            None => return 0,
+        };
+        let current_size = context.amount_targets as f64;
+        let base_size = *self.base_size.get_or_insert(current_size);
+        // positive error means the pool should grow
+        let error = match self.response {
+            MetricResponse::IncreasesWithSize => self.setpoint - value,
+            MetricResponse::DecreasesWithSize => value - self.setpoint,
+        };
+        let derivative = error - self.previous_error.unwrap_or(error);
+        self.previous_error = Some(error);
+        let integral = self.integral + self.gains.ki * error;
+        let output = base_size + self.gains.kp * error + integral + self.gains.kd * derivative;
+        let min_size = context.min_size as f64;
+        let max_size = context.max_size.max(context.min_size) as f64;
+        let saturated_high = output > max_size && error > 0.0;
+        let saturated_low = output < min_size && error < 0.0;
+        // conditional integration: integral only follows the error if that does not push
+        // the output further beyond the bounds
+        if !saturated_high && !saturated_low {
+            self.integral = integral;
+        }
+        let target_size = output.max(min_size).min(max_size).round();
+        let max_step = self.max_step as f64;
+        let advice = (target_size - current_size).max(-max_step).min(max_step);
+        debug!(
+            "PID: value: {}, error: {}, integral: {}, derivative: {}",
+            value, error, self.integral, derivative
+        );
+        info!("PID: output: {}, advice: {}", output, advice);
+        advice as i32
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intervals::IntervalMetrics;
+    use crate::performance::PerformanceTable;
+    use std::time::Instant;
+
+    fn step(policy: &mut PidPolicy, size: usize, reset_metric: f64, queue_size: i32) -> usize {
+        let performance = PerformanceTable::new(1.0);
+        let mut latest = IntervalMetrics::for_test(size, 0.0);
+        latest.derived_data.reset_metric = reset_metric;
+        let history = &[latest];
+        let context = PolicyContext {
+            history: history.iter().collect(),
+            performance: &performance,
+            amount_targets: size,
+            min_size: 1,
+            max_size: 32,
+            queue_size,
+            metric_names: &[],
+            now: Instant::now(),
+        };
+        (size as i32 + policy.scaling_advice(&context)) as usize
+    }
+
+    #[test]
+    fn holds_setpoint() {
+        // delay grows with 10 per target, 80 is reached with 8 targets
+        let gains = PidGains {
+            kp: 0.02,
+            ki: 0.04,
+            kd: 0.0,
+        };
+        let response = MetricResponse::IncreasesWithSize;
+        let mut policy = PidPolicy::new(TrackedMetric::ResetMetric, 80.0, response, gains);
+        let mut size = 2;
+        for _ in 0..30 {
+            size = step(&mut policy, size, 10.0 * size as f64, 0);
+        }
+        assert_eq!(size, 8);
+    }
+
+    #[test]
+    fn rate_limit_and_anti_windup() {
+        // queue never drains, output saturates at the maximum pool size
+        let gains = PidGains {
+            kp: 0.1,
+            ki: 0.1,
+            kd: 0.0,
+        };
+        let response = MetricResponse::DecreasesWithSize;
+        let mut policy =
+            PidPolicy::new(TrackedMetric::QueueSize, 0.0, response, gains).with_max_step(2);
+        let mut size = 4;
+        for _ in 0..5 {
+            let next = step(&mut policy, size, 0.0, 1000);
+            assert!(next - size <= 2);
+            size = next;
+        }
+        for _ in 0..50 {
+            size = step(&mut policy, size, 0.0, 1000);
+        }
+        assert_eq!(size, 32);
+        // integral stopped growing at saturation, so an empty queue shrinks the pool right away
+        let integral = policy.integral();
+        assert!(integral < 32.0);
+        assert!(step(&mut policy, size, 0.0, 0) < size);
+    }
+}
@@ -0,0 +1,281 @@
+use std::any::Any;
+
+use log::{debug, info};
+
+use super::{PolicyContext, ScalingPolicy, UNBOUNDED_SEARCH_LIMIT};
+
+/// Universal Scalability Law: X(N) = lambda * N / (1 + sigma * (N - 1) + kappa * N * (N - 1))
+/// lambda: throughput of a single target
+/// sigma: contention coefficient (serialized fraction of the work)
+/// kappa: coherency coefficient (cost of keeping targets in sync)
+#[derive(Clone, Copy, Debug)]
+pub struct UslModel {
+    pub lambda: f64,
+    pub sigma: f64,
+    pub kappa: f64,
+}
+
+impl UslModel {
+    /// weighted least squares fit to (pool size, throughput, weight) points
+    /// uses the linearization N / X = a + b * (N - 1) + c * N * (N - 1)
+    /// with a = 1 / lambda, b = sigma / lambda, c = kappa / lambda
+    /// returns None if there are less than 3 distinct pool sizes or the fit is degenerate
+    pub fn fit(points: &[(f64, f64, f64)]) -> Option<UslModel> {
+        let mut sizes: Vec<f64> = points
+            .iter()
+            .filter(|(size, throughput, weight)| *size >= 1.0 && *throughput > 0.0 && *weight > 0.0)
+            .map(|(size, _, _)| *size)
+            .collect();
+        sizes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        sizes.dedup();
+        if sizes.len() < 3 {
+            return None;
+        }
+        // normal equations of the weighted regression
+        let mut matrix = [[0.0; 3]; 3];
+        let mut vector = [0.0; 3];
+        for &(size, throughput, weight) in points {
+            if size < 1.0 || throughput <= 0.0 || weight <= 0.0 {
+                continue;
+            }
+            let regressors = [1.0, size - 1.0, size * (size - 1.0)];
+            let y = size / throughput;
+            for row in 0..3 {
+                for column in 0..3 {
+                    matrix[row][column] += weight * regressors[row] * regressors[column];
+                }
+                vector[row] += weight * regressors[row] * y;
+            }
+        }
+        let [a, b, c] = solve_3x3(matrix, vector)?;
+        if a <= 0.0 {
+            return None;
+        }
+        // negative coefficients are not physical, they stem from noise
+        Some(UslModel {
+            lambda: 1.0 / a,
+            sigma: (b / a).max(0.0),
+            kappa: (c / a).max(0.0),
+        })
+    }
+
+    /// predicted throughput for the given pool size
+    pub fn throughput(&self, size: f64) -> f64 {
+        self.lambda * size / (1.0 + self.sigma * (size - 1.0) + self.kappa * size * (size - 1.0))
+    }
+
+    /// pool size of maximum predicted throughput
+    /// None if throughput keeps growing with the pool size (no coherency cost)
+    pub fn peak(&self) -> Option<f64> {
+        if self.kappa <= 0.0 || self.sigma >= 1.0 {
+            return None;
+        }
+        Some(((1.0 - self.sigma) / self.kappa).sqrt())
+    }
+}
+
+/// solve the linear system with gaussian elimination (partial pivoting)
+fn solve_3x3(mut matrix: [[f64; 3]; 3], mut vector: [f64; 3]) -> Option<[f64; 3]> {
+    for column in 0..3 {
+        let pivot = (column..3).max_by(|&a, &b| {
+            matrix[a][column]
+                .abs()
+                .partial_cmp(&matrix[b][column].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        vector.swap(column, pivot);
+        for row in column + 1..3 {
+            let factor = matrix[row][column] / matrix[column][column];
+            let pivot_row = matrix[column];
+            for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row.iter()).skip(column) {
+                *value -= factor * pivot_value;
+            }
+            vector[row] -= factor * vector[column];
+        }
+    }
+    let mut result = [0.0; 3];
+    for row in (0..3).rev() {
+        let mut sum = vector[row];
+        for k in row + 1..3 {
+            sum -= matrix[row][k] * result[k];
+        }
+        result[row] = sum / matrix[row][row];
+    }
+    Some(result)
+}
+
+/// fits the Universal Scalability Law to the observed scale metric per pool size
+/// and moves to the pool size at the predicted throughput peak
+/// until 3 pool sizes are known the pool size is doubled to collect data points
+pub struct UslPolicy {
+    /// amount of valid intervals at a pool size before the next decision
+    samples_per_size: usize,
+    model: Option<UslModel>,
+}
+
+impl UslPolicy {
+    pub fn new(samples_per_size: usize) -> Self {
+        UslPolicy {
+            samples_per_size: samples_per_size.max(1),
+            model: None,
+        }
+    }
+
+    /// most recently fitted model
+    pub fn model(&self) -> Option<&UslModel> {
+        self.model.as_ref()
+    }
+}
+
+impl ScalingPolicy for UslPolicy {
+    fn scaling_advice(&mut self, context: &PolicyContext) -> i32 {
+        if context.intervals_at_current_size() < self.samples_per_size {
+            debug!("USL: collecting samples for current pool size");
+            return 0;
+        }
+        let points: Vec<(f64, f64, f64)> = context
+            .performance
+            .entries()
+            .map(|(size, statistics)| (size as f64, statistics.mean, statistics.weight))
+            .collect();
+        self.model = UslModel::fit(&points);
+        let current_size = context.amount_targets;
+        // a peak far beyond the observed sizes is not trusted without an upper bound
+        let max_size = if context.max_size == usize::MAX {
+            UNBOUNDED_SEARCH_LIMIT.max(current_size)
+        } else {
+            context.max_size
+        };
+        let bounded = |size: usize| size.max(context.min_size).min(max_size);
+        let doubled = current_size
+            .saturating_mul(2)
+            .max(current_size.saturating_add(1));
+        let target_size = match self.model {
+            Some(model) => {
+                info!(
+                    "USL: lambda: {}, sigma: {}, kappa: {}, peak: {:?}",
+                    model.lambda,
+                    model.sigma,
+                    model.kappa,
+                    model.peak()
+                );
+                match model.peak() {
+                    Some(peak) => bounded(peak.round() as usize),
+                    // no retrograde scaling observed yet, keep growing
+                    None => bounded(doubled),
+                }
+            }
+            // not enough distinct pool sizes, probe a larger (or if at maximum a smaller) one
+            None => {
+                let probe = bounded(doubled);
+                if probe == current_size {
+                    bounded(current_size / 2)
+                } else {
+                    probe
+                }
+            }
+        };
+        let advice = target_size as i64 - current_size as i64;
+        advice.max(i32::MIN as i64).min(i32::MAX as i64) as i32
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intervals::IntervalMetrics;
+    use crate::performance::PerformanceTable;
+    use std::time::Instant;
+
+    fn usl_points(model: &UslModel, sizes: &[usize]) -> Vec<(f64, f64, f64)> {
+        sizes
+            .iter()
+            .map(|&size| (size as f64, model.throughput(size as f64), 1.0))
+            .collect()
+    }
+
+    #[test]
+    fn fit_recovers_coefficients() {
+        let model = UslModel {
+            lambda: 100.0,
+            sigma: 0.05,
+            kappa: 0.002,
+        };
+        let fitted = UslModel::fit(&usl_points(&model, &[1, 2, 4, 8, 16])).unwrap();
+        assert!((fitted.lambda - 100.0).abs() < 1e-6);
+        assert!((fitted.sigma - 0.05).abs() < 1e-6);
+        assert!((fitted.kappa - 0.002).abs() < 1e-6);
+        assert!((fitted.peak().unwrap() - model.peak().unwrap()).abs() < 1e-6);
+        assert!(UslModel::fit(&usl_points(&model, &[1, 2])).is_none());
+    }
+
+    #[test]
+    fn policy_jumps_to_peak() {
+        let model = UslModel {
+            lambda: 100.0,
+            sigma: 0.1,
+            kappa: 0.01,
+        };
+        let mut performance = PerformanceTable::new(1.0);
+        for &size in &[1, 2, 4] {
+            performance.add(size, model.throughput(size as f64));
+        }
+        let latest = IntervalMetrics::for_test(4, model.throughput(4.0));
+        let mut context = PolicyContext {
+            history: Vec::new(),
+            performance: &performance,
+            amount_targets: 4,
+            min_size: 1,
+            max_size: 64,
+            queue_size: 0,
+            metric_names: &[],
+            now: Instant::now(),
+        };
+        let mut policy = UslPolicy::new(1);
+        // no samples at current size yet
+        assert_eq!(policy.scaling_advice(&context), 0);
+        // peak at sqrt(0.9 / 0.01) = 9.49
+        context.history = vec![&latest];
+        assert_eq!(policy.scaling_advice(&context), 5);
+        context.max_size = 6;
+        assert_eq!(policy.scaling_advice(&context), 2);
+    }
+
+    #[test]
+    fn unbounded_peak_is_capped() {
+        // peak at sqrt(0.9 / 1e-6) = 949
+        let model = UslModel {
+            lambda: 100.0,
+            sigma: 0.1,
+            kappa: 1e-6,
+        };
+        let mut performance = PerformanceTable::new(1.0);
+        for &size in &[1, 2, 4] {
+            performance.add(size, model.throughput(size as f64));
+        }
+        let latest = IntervalMetrics::for_test(4, model.throughput(4.0));
+        let context = PolicyContext {
+            history: vec![&latest],
+            performance: &performance,
+            amount_targets: 4,
+            min_size: 1,
+            max_size: usize::MAX,
+            queue_size: 0,
+            metric_names: &[],
+            now: Instant::now(),
+        };
+        let mut policy = UslPolicy::new(1);
+        assert_eq!(
+            policy.scaling_advice(&context),
+            UNBOUNDED_SEARCH_LIMIT as i32 - 4
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, info};
+
+use crate::events::{AdviceEvent, AdviceObserver, AdviceReason, TracesetCounters, WatchdogEvent};
+use crate::AdapterState;
+
+// stalled clients give up their connection thread after this
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const STATES: [&str; 4] = ["startup", "scaling", "exploring", "settled"];
+const REASONS: [(AdviceReason, &str); 12] = [
+    (AdviceReason::InvalidInterval, "invalid_interval"),
+    (AdviceReason::MixedInterval, "mixed_interval"),
+    (AdviceReason::CollectingSamples, "collecting_samples"),
+    (AdviceReason::Settling, "settling"),
+    (AdviceReason::StateMachine, "state_machine"),
+    (AdviceReason::JumpToBest, "jump_to_best"),
+    (AdviceReason::Policy, "policy"),
+    (AdviceReason::GuardViolated, "guard_violated"),
+    (AdviceReason::GuardLimit, "guard_limit"),
+    (AdviceReason::Bounded, "bounded"),
+    (AdviceReason::QueueEmpty, "queue_empty"),
+    (AdviceReason::Watchdog, "watchdog"),
+];
+const HEALTH_ISSUES: [&str; 3] = ["zero_counters", "decreasing_counters", "long_interval"];
+
+fn state_label(state: &AdapterState) -> &'static str {
+    match state {
+        AdapterState::Startup => STATES[0],
+        AdapterState::Scaling(_) => STATES[1],
+        AdapterState::Exploring(_) => STATES[2],
+        AdapterState::Settled(_, _) => STATES[3],
+    }
+}
+
+#[derive(Default)]
+struct ExportedMetrics {
+    latest: Option<AdviceEvent>,
+    advice_up: u64,
+    advice_down: u64,
+    advice_none: u64,
+    decisions: [u64; REASONS.len()],
+    watchdog_trips: [u64; HEALTH_ISSUES.len()],
+}
+
+/// exports the latest decision of the adapter in the prometheus text format
+/// register a clone as observer, then serve it over http or write it to a textfile
+#[derive(Clone, Default)]
+pub struct PrometheusExporter {
+    metrics: Arc<Mutex<ExportedMetrics>>,
+    /// renderings for the textfile writer thread, Sender is not Sync
+    textfile: Option<Arc<Mutex<Sender<String>>>>,
+}
+
+impl PrometheusExporter {
+    pub fn new() -> Self {
+        PrometheusExporter::default()
+    }
+
+    /// rewrite the file after every decision, for the node exporter textfile collector
+    /// the file is written by a background thread, so deciding never waits for the disk
+    pub fn with_textfile(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let (sender, receiver) = mpsc::channel::<String>();
+        // ends when the last clone of the exporter is dropped
+        thread::spawn(move || {
+            while let Ok(mut rendered) = receiver.recv() {
+                // only the latest rendering is worth writing
+                while let Ok(newer) = receiver.try_recv() {
+                    rendered = newer;
+                }
+                if let Err(e) = write_atomically(&path, &rendered) {
+                    info!("PROMETHEUS: could not write textfile {:?}: {}", path, e);
+                }
+            }
+        });
+        self.textfile = Some(Arc::new(Mutex::new(sender)));
+        self
+    }
+
+    /// current metrics in the prometheus text format
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let mut out = String::new();
+        gauge_header(
+            &mut out,
+            "scaling_adapter_state",
+            "current state of the adapter",
+        );
+        let current_state = metrics
+            .latest
+            .as_ref()
+            .map(|event| state_label(&event.new_state));
+        for state in STATES.iter() {
+            let value = if current_state == Some(state) { 1 } else { 0 };
+            let _ = writeln!(
+                out,
+                "scaling_adapter_state{{state=\"{}\"}} {}",
+                state, value
+            );
+        }
+        counter_header(
+            &mut out,
+            "scaling_adapter_advice_total",
+            "advice given, by direction",
+        );
+        for (direction, count) in [
+            ("up", metrics.advice_up),
+            ("down", metrics.advice_down),
+            ("none", metrics.advice_none),
+        ]
+        .iter()
+        {
+            let _ = writeln!(
+                out,
+                "scaling_adapter_advice_total{{direction=\"{}\"}} {}",
+                direction, count
+            );
+        }
+        counter_header(
+            &mut out,
+            "scaling_adapter_decisions_total",
+            "decisions, by reason",
+        );
+        for ((_, reason), count) in REASONS.iter().zip(metrics.decisions.iter()) {
+            let _ = writeln!(
+                out,
+                "scaling_adapter_decisions_total{{reason=\"{}\"}} {}",
+                reason, count
+            );
+        }
+        counter_header(
+            &mut out,
+            "scaling_adapter_watchdog_total",
+            "intervals rejected by the watchdog, by issue",
+        );
+        for (issue, count) in HEALTH_ISSUES.iter().zip(metrics.watchdog_trips.iter()) {
+            let _ = writeln!(
+                out,
+                "scaling_adapter_watchdog_total{{issue=\"{}\"}} {}",
+                issue, count
+            );
+        }
+        let event = match &metrics.latest {
+            Some(event) => event,
+            None => return out,
+        };
+        gauge(
+            &mut out,
+            "scaling_adapter_last_advice",
+            "latest advice",
+            event.advice as f64,
+        );
+        gauge(
+            &mut out,
+            "scaling_adapter_queue_size",
+            "queue size passed with the latest advice request",
+            event.queue_size as f64,
+        );
+        gauge(
+            &mut out,
+            "scaling_adapter_degraded",
+            "1 if the adapter runs without kernel data",
+            if event.degraded { 1.0 } else { 0.0 },
+        );
+        let TracesetCounters {
+            read_bytes,
+            write_bytes,
+            blkio_delay,
+            amount_targets,
+        } = event.traceset;
+        gauge(
+            &mut out,
+            "scaling_adapter_pool_size",
+            "amount of traced targets",
+            amount_targets as f64,
+        );
+        counter(
+            &mut out,
+            "scaling_adapter_traceset_read_bytes_total",
+            "bytes read by the traced targets",
+            read_bytes,
+        );
+        counter(
+            &mut out,
+            "scaling_adapter_traceset_write_bytes_total",
+            "bytes written by the traced targets",
+            write_bytes,
+        );
+        counter(
+            &mut out,
+            "scaling_adapter_traceset_blkio_delay_total",
+            "block io delay of the traced targets",
+            blkio_delay,
+        );
+        if let Some(interval) = &event.interval {
+            gauge(
+                &mut out,
+                "scaling_adapter_scale_metric",
+                "scale metric of the latest interval",
+                interval.scale_metric,
+            );
+            gauge(
+                &mut out,
+                "scaling_adapter_scale_metric_filtered",
+                "filtered scale metric of the latest interval",
+                interval.filtered_scale_metric,
+            );
+            gauge(
+                &mut out,
+                "scaling_adapter_reset_metric",
+                "reset metric of the latest interval",
+                interval.reset_metric,
+            );
+            labeled_gauges(
+                &mut out,
+                "scaling_adapter_guard_value",
+                "guard metrics of the latest interval",
+                &interval.guard_values,
+            );
+            labeled_gauges(
+                &mut out,
+                "scaling_adapter_metric_value",
+                "named metrics of the latest interval",
+                &interval.named_values,
+            );
+        }
+        out
+    }
+
+    /// write the metrics to path, via a temporary file so the collector never reads a partial file
+    pub fn write_textfile(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_atomically(path.as_ref(), &self.render())
+    }
+
+    /// serve GET /metrics on addr from a background thread, one thread per connection
+    /// returns the bound address (useful with port 0)
+    pub fn serve(&self, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        info!(
+            "PROMETHEUS: serving metrics on http://{}/metrics",
+            local_addr
+        );
+        let exporter = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let exporter = exporter.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = exporter.handle_request(stream) {
+                                debug!("PROMETHEUS: request failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => debug!("PROMETHEUS: connection failed: {}", e),
+                }
+            }
+        });
+        Ok(local_addr)
+    }
+
+    fn handle_request(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+        let mut parts = request_line.split_whitespace();
+        let (status, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", self.render()),
+            _ => ("404 Not Found", String::from("not found\n")),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+}
+
+impl AdviceObserver for PrometheusExporter {
+    fn on_advice(&self, event: &AdviceEvent) {
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+            match event.advice {
+                a if a > 0 => metrics.advice_up += 1,
+                a if a < 0 => metrics.advice_down += 1,
+                _ => metrics.advice_none += 1,
+            }
+            if let Some(index) = REASONS
+                .iter()
+                .position(|(reason, _)| *reason == event.reason)
+            {
+                metrics.decisions[index] += 1;
+            }
+            metrics.latest = Some(event.clone());
+        }
+        if let Some(sender) = &self.textfile {
+            // writer thread only stops with the exporter
+            let _ = sender.lock().unwrap().send(self.render());
+        }
+    }
+
+    fn on_watchdog(&self, event: &WatchdogEvent) {
+        let mut metrics = self.metrics.lock().unwrap();
+        if let Some(index) = HEALTH_ISSUES
+            .iter()
+            .position(|issue| *issue == event.issue.name())
+        {
+            metrics.watchdog_trips[index] += 1;
+        }
+    }
+}
+
+fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let tmp_path = path.with_extension("prom.tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(tmp_path, path)
+}
+
+fn gauge_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+}
+
+fn counter_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", name, help, name);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    gauge_header(out, name, help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    counter_header(out, name, help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn labeled_gauges(out: &mut String, name: &str, help: &str, values: &[(String, f64)]) {
+    if values.is_empty() {
+        return;
+    }
+    gauge_header(out, name, help);
+    for (label, value) in values.iter() {
+        let _ = writeln!(
+            out,
+            "{}{{name=\"{}\"}} {}",
+            name,
+            escape_label_value(label),
+            value
+        );
+    }
+}
+
+/// label values are quoted, backslash, double quote and newline must be escaped
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::IntervalSummary;
+    use crate::watchdog::HealthIssue;
+    use std::io::Read;
+    use std::time::SystemTime;
+
+    #[test]
+    fn metrics_endpoint() {
+        let textfile = std::env::temp_dir().join(format!(
+            "scaling_adapter_metrics_{}.prom",
+            std::process::id()
+        ));
+        let exporter = PrometheusExporter::new().with_textfile(&textfile);
+        let event = AdviceEvent {
+            timestamp: SystemTime::now(),
+            previous_state: AdapterState::Startup,
+            new_state: AdapterState::Scaling(2),
+            queue_size: 7,
+            traceset: TracesetCounters {
+                read_bytes: 10,
+                write_bytes: 2048,
+                blkio_delay: 5,
+                amount_targets: 3,
+            },
+            interval: Some(IntervalSummary {
+                pool_size: 3,
+                duration: Duration::from_millis(1000),
+                scale_metric: 2.5,
+                filtered_scale_metric: 2.0,
+                reset_metric: 0.5,
+                guard_values: vec![(String::from("blkio"), 0.25)],
+                named_values: vec![(String::from("odd \"name\\\n"), 1.5)],
+            }),
+            advice: 2,
+            reason: AdviceReason::StateMachine,
+            degraded: false,
+            unfollowed_target: None,
+            violated_guards: Vec::new(),
+            guard_limit: None,
+        };
+        exporter.on_advice(&event);
+        exporter.on_advice(&AdviceEvent {
+            advice: 0,
+            reason: AdviceReason::CollectingSamples,
+            ..event
+        });
+        exporter.on_watchdog(&WatchdogEvent {
+            timestamp: SystemTime::now(),
+            issue: HealthIssue::ZeroCounters,
+            amount_targets: 3,
+            queue_size: 7,
+            pinned_size: None,
+        });
+        let addr = exporter.serve("127.0.0.1:0").unwrap();
+        // a client that never sends its request does not block the others
+        let _stalled = TcpStream::connect(addr).unwrap();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("scaling_adapter_state{state=\"scaling\"} 1"));
+        assert!(response.contains("scaling_adapter_state{state=\"startup\"} 0"));
+        assert!(response.contains("scaling_adapter_pool_size 3"));
+        assert!(response.contains("scaling_adapter_degraded 0"));
+        assert!(response.contains("scaling_adapter_scale_metric 2.5"));
+        assert!(response.contains("scaling_adapter_advice_total{direction=\"up\"} 1"));
+        assert!(response.contains("scaling_adapter_advice_total{direction=\"none\"} 1"));
+        assert!(
+            response.contains("scaling_adapter_decisions_total{reason=\"collecting_samples\"} 1")
+        );
+        assert!(response.contains("scaling_adapter_traceset_write_bytes_total 2048"));
+        assert!(response.contains("scaling_adapter_guard_value{name=\"blkio\"} 0.25"));
+        assert!(response.contains("scaling_adapter_watchdog_total{issue=\"zero_counters\"} 1"));
+        assert!(response.contains("scaling_adapter_metric_value{name=\"odd \\\"name\\\\\\n\"} 1.5"));
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404"));
+        // written by the background thread
+        let mut written = String::new();
+        for _ in 0..100 {
+            written = fs::read_to_string(&textfile).unwrap_or_default();
+            if written.contains("scaling_adapter_decisions_total{reason=\"collecting_samples\"} 1")
+            {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(written.contains("scaling_adapter_advice_total{direction=\"none\"} 1"));
+        let _ = fs::remove_file(&textfile);
+    }
+}
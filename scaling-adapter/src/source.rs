@@ -0,0 +1,142 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use tracesets::{Traceset, TracesetSnapshot};
+
+/// provides the traced data of the targets (threads) of the pool
+/// implemented by the kernel traceset, can be replaced by a synthetic source for simulations
+pub trait TracesetSource: Send + Sync {
+    /// cumulative data of all targets since the source was created
+    fn get_snapshot(&self) -> TracesetSnapshot;
+    fn register_target(&mut self, target: i32) -> bool;
+    fn deregister_target(&mut self, target: i32) -> bool;
+    fn get_amount_targets(&self) -> usize;
+}
+
+impl TracesetSource for Traceset {
+    fn get_snapshot(&self) -> TracesetSnapshot {
+        Traceset::get_snapshot(self)
+    }
+
+    fn register_target(&mut self, target: i32) -> bool {
+        Traceset::register_target(self, target)
+    }
+
+    fn deregister_target(&mut self, target: i32) -> bool {
+        Traceset::deregister_target(self, target)
+    }
+
+    fn get_amount_targets(&self) -> usize {
+        Traceset::get_amount_targets(self)
+    }
+}
+
+/// source without kernel data, used in degraded mode when the traceset is unavailable
+/// only keeps track of the targets, all counters stay 0
+#[derive(Default)]
+pub struct DegradedSource {
+    targets: HashSet<i32>,
+}
+
+impl DegradedSource {
+    pub fn new() -> Self {
+        DegradedSource::default()
+    }
+}
+
+impl TracesetSource for DegradedSource {
+    fn get_snapshot(&self) -> TracesetSnapshot {
+        TracesetSnapshot {
+            read_bytes: 0,
+            write_bytes: 0,
+            blkio_delay: 0,
+            syscalls_data: HashMap::new(),
+            targets: self.targets.clone(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    fn register_target(&mut self, target: i32) -> bool {
+        self.targets.insert(target)
+    }
+
+    fn deregister_target(&mut self, target: i32) -> bool {
+        self.targets.remove(&target)
+    }
+
+    fn get_amount_targets(&self) -> usize {
+        self.targets.len()
+    }
+}
+
+#[cfg(test)]
+/// synthetic source for tests, every target writes bytes_per_target bytes per snapshot
+pub(crate) struct TestSource {
+    targets: HashSet<i32>,
+    bytes_per_target: u64,
+    write_bytes: std::sync::Mutex<u64>,
+}
+
+#[cfg(test)]
+impl TestSource {
+    pub(crate) fn new(bytes_per_target: u64) -> Self {
+        TestSource {
+            targets: HashSet::new(),
+            bytes_per_target,
+            write_bytes: std::sync::Mutex::new(0),
+        }
+    }
+
+    /// targets without any traced activity
+    pub(crate) fn idle() -> Self {
+        TestSource::new(0)
+    }
+}
+
+#[cfg(test)]
+impl TracesetSource for TestSource {
+    fn get_snapshot(&self) -> TracesetSnapshot {
+        let mut write_bytes = self.write_bytes.lock().unwrap();
+        *write_bytes += self.targets.len() as u64 * self.bytes_per_target;
+        TracesetSnapshot {
+            read_bytes: 0,
+            write_bytes: *write_bytes,
+            blkio_delay: 0,
+            syscalls_data: HashMap::new(),
+            targets: self.targets.clone(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    fn register_target(&mut self, target: i32) -> bool {
+        self.targets.insert(target)
+    }
+
+    fn deregister_target(&mut self, target: i32) -> bool {
+        self.targets.remove(&target)
+    }
+
+    fn get_amount_targets(&self) -> usize {
+        self.targets.len()
+    }
+}
+
+/// counts the jobs completed by the pool, shared between the pool and the adapter
+/// lock-free, so workers can report every job without locking the adapter
+#[derive(Clone, Debug, Default)]
+pub struct JobCounter {
+    completed: Arc<AtomicU64>,
+}
+
+impl JobCounter {
+    pub fn job_completed(&self) {
+        self.completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// jobs completed since the adapter was created
+    pub fn get(&self) -> u64 {
+        self.completed.load(Ordering::Relaxed)
+    }
+}
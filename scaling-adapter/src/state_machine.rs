@@ -0,0 +1,251 @@
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+/// how long the state machine stays settled before exploring again
+pub const SETTLE_TIMEOUT: Duration = Duration::from_millis(2000);
+
+/// outcome of comparing the latest pool size with the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trend {
+    Improved,
+    Degraded,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub fn get_opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    pub fn from_step_size(step_size: i32) -> Direction {
+        if step_size >= 0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+}
+
+/// state of the built-in state machine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdapterState {
+    Startup,
+    Scaling(i32),
+    Exploring(Direction),
+    Settled(Instant, Direction),
+}
+
+/// everything the state machine bases its decision on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observations {
+    pub now: Instant,
+    /// registered targets (current pool size)
+    pub amount_targets: usize,
+    /// latest pool size compared with the previous one, only used while scaling or exploring
+    pub trend: Trend,
+}
+
+/// decision of the built-in state machine: new state and advice
+/// the advice never shrinks the pool below one target, if it had to be limited
+/// the state machine settles and explores upwards next
+pub fn decide(state: AdapterState, observations: &Observations) -> (AdapterState, i32) {
+    let (new_state, advice) = match state {
+        AdapterState::Startup => (AdapterState::Scaling(1), 1),
+        AdapterState::Settled(timeout, direction) => {
+            if observations.now > timeout {
+                decide_settled(direction)
+            } else {
+                (state, 0)
+            }
+        }
+        AdapterState::Scaling(step_size) => decide_scaling(step_size, observations),
+        AdapterState::Exploring(direction) => decide_exploring(direction, observations),
+    };
+    let min_advice = 1 - observations.amount_targets as i32;
+    if advice < min_advice {
+        let settled = AdapterState::Settled(observations.now + SETTLE_TIMEOUT, Direction::Down);
+        return (settled, min_advice);
+    }
+    (new_state, advice)
+}
+
+fn decide_settled(last_direction: Direction) -> (AdapterState, i32) {
+    match last_direction {
+        Direction::Up => {
+            debug!("{}", "Exploring DOWN");
+            (AdapterState::Exploring(Direction::Down), -1)
+        }
+        Direction::Down => {
+            debug!("{}", "Exploring UP");
+            (AdapterState::Exploring(Direction::Up), 1)
+        }
+    }
+}
+
+fn decide_exploring(direction: Direction, observations: &Observations) -> (AdapterState, i32) {
+    let step_size = match direction {
+        Direction::Up => 1,
+        Direction::Down => -1,
+    };
+    // enter scaling state
+    if observations.trend == Trend::Improved {
+        (AdapterState::Scaling(step_size), step_size)
+    // scale back to previous & enter settled state
+    // set timeout for next explore move
+    } else {
+        (
+            AdapterState::Settled(observations.now + SETTLE_TIMEOUT, direction),
+            -step_size,
+        )
+    }
+}
+
+fn decide_scaling(step_size: i32, observations: &Observations) -> (AdapterState, i32) {
+    let direction = Direction::from_step_size(step_size);
+    // step sizes will always grow 1 -> 2 -> 4
+    let new_step_size = if step_size.abs() < 4 {
+        step_size + 1
+    } else {
+        step_size
+    };
+    // scale further
+    if observations.trend == Trend::Improved {
+        (AdapterState::Scaling(new_step_size), new_step_size)
+    // scale back to previous & enter settled state
+    // set no timeout, so next action will be exploring step
+    } else if observations.trend == Trend::Degraded && observations.amount_targets > 1 {
+        (
+            AdapterState::Settled(observations.now, direction),
+            -step_size,
+        )
+    // enter settled state
+    } else {
+        (AdapterState::Settled(observations.now, direction), 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn direction() -> impl Strategy<Value = Direction> {
+        prop_oneof![Just(Direction::Up), Just(Direction::Down)]
+    }
+
+    fn trend() -> impl Strategy<Value = Trend> {
+        prop_oneof![
+            Just(Trend::Improved),
+            Just(Trend::Degraded),
+            Just(Trend::Unchanged)
+        ]
+    }
+
+    /// states with timeouts relative to the start instant (in ms)
+    fn state(start: Instant) -> impl Strategy<Value = AdapterState> {
+        prop_oneof![
+            Just(AdapterState::Startup),
+            (-4i32..=4).prop_map(AdapterState::Scaling),
+            direction().prop_map(AdapterState::Exploring),
+            (0u64..10000, direction()).prop_map(move |(timeout, direction)| {
+                AdapterState::Settled(start + Duration::from_millis(timeout), direction)
+            }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn never_below_one_target(
+            (start, state) in Just(Instant::now()).prop_flat_map(|start| (Just(start), state(start))),
+            now in 0u64..20000,
+            amount_targets in 1usize..64,
+            trend in trend(),
+        ) {
+            let observations = Observations {
+                now: start + Duration::from_millis(now),
+                amount_targets,
+                trend,
+            };
+            let (_state, advice) = decide(state, &observations);
+            prop_assert!(amount_targets as i32 + advice >= 1);
+        }
+
+        #[test]
+        fn state_matches_advice(
+            (start, state) in Just(Instant::now()).prop_flat_map(|start| (Just(start), state(start))),
+            now in 0u64..20000,
+            amount_targets in 1usize..64,
+            trend in trend(),
+        ) {
+            let observations = Observations {
+                now: start + Duration::from_millis(now),
+                amount_targets,
+                trend,
+            };
+            let (new_state, advice) = decide(state, &observations);
+            prop_assert_ne!(new_state, AdapterState::Startup);
+            // settled states revert or hold, any advice within the bounds matches
+            match new_state {
+                AdapterState::Scaling(step_size) => prop_assert_eq!(advice, step_size),
+                AdapterState::Exploring(Direction::Up) => prop_assert_eq!(advice, 1),
+                AdapterState::Exploring(Direction::Down) => prop_assert_eq!(advice, -1),
+                _ => {}
+            }
+        }
+
+        #[test]
+        fn settled_eventually_explores(
+            direction in direction(),
+            timeout in 0u64..10000,
+            // a single target cannot explore downwards, see limited_advice_settles
+            amount_targets in 2usize..64,
+            trend in trend(),
+        ) {
+            let start = Instant::now();
+            let state = AdapterState::Settled(start + Duration::from_millis(timeout), direction);
+            let mut observations = Observations {
+                now: start,
+                amount_targets,
+                trend,
+            };
+            // stays settled until the timeout
+            prop_assert_eq!(decide(state, &observations), (state, 0));
+            observations.now = start + Duration::from_millis(timeout + 1);
+            let (new_state, _advice) = decide(state, &observations);
+            prop_assert_eq!(new_state, AdapterState::Exploring(direction.get_opposite()));
+        }
+    }
+
+    #[test]
+    fn limited_advice_settles() {
+        let now = Instant::now();
+        let observations = Observations {
+            now,
+            amount_targets: 1,
+            trend: Trend::Unchanged,
+        };
+        // exploring downwards is not possible with a single target
+        let state = AdapterState::Settled(now - Duration::from_millis(1), Direction::Up);
+        let settled = AdapterState::Settled(now + SETTLE_TIMEOUT, Direction::Down);
+        assert_eq!(decide(state, &observations), (settled, 0));
+        // next exploration is upwards
+        let observations = Observations {
+            now: now + SETTLE_TIMEOUT * 2,
+            ..observations
+        };
+        assert_eq!(
+            decide(settled, &observations),
+            (AdapterState::Exploring(Direction::Up), 1)
+        );
+    }
+}
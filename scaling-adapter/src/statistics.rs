@@ -0,0 +1,254 @@
+/// arithmetic mean, NaN for empty samples
+pub fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+/// unbiased sample variance, 0 for samples with less than 2 values
+pub fn variance(samples: &[f64]) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(samples);
+    let squared_diffs: f64 = samples.iter().map(|x| (x - mean).powi(2)).sum();
+    squared_diffs / (samples.len() - 1) as f64
+}
+
+/// one-sided Welch t-test for H1: mean(a) > mean(b)
+/// returns the p-value, 1.0 if the test can not be performed
+pub fn welch_t_test_greater(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() < 2 || b.len() < 2 {
+        return 1.0;
+    }
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+    let (var_a, var_b) = (variance(a) / n_a, variance(b) / n_b);
+    let mean_diff = mean(a) - mean(b);
+    let std_error = (var_a + var_b).sqrt();
+    // both samples are constant, the result is certain either way
+    if std_error == 0.0 {
+        return if mean_diff > 0.0 { 0.0 } else { 1.0 };
+    }
+    let t = mean_diff / std_error;
+    // Welch-Satterthwaite approximation of the degrees of freedom
+    let df = (var_a + var_b).powi(2) / (var_a.powi(2) / (n_a - 1.0) + var_b.powi(2) / (n_b - 1.0));
+    1.0 - student_t_cdf(t, df)
+}
+
+/// one-sided Mann-Whitney U test for H1: values of a tend to be larger than values of b
+/// uses the normal approximation with tie correction
+/// returns the p-value, 1.0 if the test can not be performed
+pub fn mann_whitney_u_greater(a: &[f64], b: &[f64]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 1.0;
+    }
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+    // count pairs where a wins, ties count half
+    let mut u_a = 0.0;
+    for x in a {
+        for y in b {
+            if x > y {
+                u_a += 1.0;
+            } else if (x - y).abs() == 0.0 {
+                u_a += 0.5;
+            }
+        }
+    }
+    let n = n_a + n_b;
+    let mut combined: Vec<f64> = a.iter().chain(b.iter()).copied().collect();
+    combined.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    let mut tie_correction = 0.0;
+    let mut i = 0;
+    while i < combined.len() {
+        let mut j = i;
+        while j + 1 < combined.len() && combined[j + 1] == combined[i] {
+            j += 1;
+        }
+        let ties = (j - i + 1) as f64;
+        tie_correction += ties.powi(3) - ties;
+        i = j + 1;
+    }
+    let mean_u = n_a * n_b / 2.0;
+    let var_u = n_a * n_b / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)));
+    if var_u <= 0.0 {
+        return 1.0;
+    }
+    // continuity correction towards the mean
+    let z = (u_a - mean_u - 0.5) / var_u.sqrt();
+    1.0 - normal_cdf(z)
+}
+
+/// smallest p-value the Mann-Whitney U test can give for two samples of size n,
+/// reached when every value of one sample is larger than every value of the other
+pub fn mann_whitney_u_min_p_value(n: usize) -> f64 {
+    let larger: Vec<f64> = (n..2 * n).map(|x| x as f64).collect();
+    let smaller: Vec<f64> = (0..n).map(|x| x as f64).collect();
+    mann_whitney_u_greater(&larger, &smaller)
+}
+
+/// cumulative distribution function of the standard normal distribution
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / std::f64::consts::SQRT_2)
+}
+
+/// cumulative distribution function of Student's t-distribution
+pub fn student_t_cdf(t: f64, df: f64) -> f64 {
+    let x = df / (df + t * t);
+    let tail = 0.5 * regularized_incomplete_beta(df / 2.0, 0.5, x);
+    if t > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// complementary error function, fractional error below 1.2e-7 (Numerical Recipes erfcc)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let result = t * poly.exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+/// natural logarithm of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    let coefficients = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000_000_000_190_015;
+    for (i, c) in coefficients.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// regularized incomplete beta function I_x(a, b)
+fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // continued fraction converges quickly for x < (a + 1) / (a + b + 2)
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// continued fraction for the incomplete beta function (modified Lentz's method)
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let max_iterations = 200;
+    let epsilon = 3.0e-12;
+    let tiny = 1.0e-300;
+    let qab = a + b;
+    let qap = a + 1.0;
+    let qam = a - 1.0;
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < tiny {
+        d = tiny;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..=max_iterations {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        // even step
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        // odd step
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < epsilon {
+            break;
+        }
+    }
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64, tolerance: f64) {
+        assert!((a - b).abs() < tolerance, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn distributions() {
+        assert_close(normal_cdf(0.0), 0.5, 1e-6);
+        assert_close(normal_cdf(1.96), 0.975, 1e-4);
+        assert_close(student_t_cdf(0.0, 5.0), 0.5, 1e-9);
+        // t_{0.975, 10} = 2.228
+        assert_close(student_t_cdf(2.228, 10.0), 0.975, 1e-3);
+        assert_close(student_t_cdf(-2.228, 10.0), 0.025, 1e-3);
+    }
+
+    #[test]
+    fn significance_tests() {
+        let low = [10.0, 11.0, 9.5, 10.5, 10.2];
+        let high = [14.0, 15.5, 13.8, 14.9, 15.1];
+        assert!(welch_t_test_greater(&high, &low) < 0.01);
+        assert!(welch_t_test_greater(&low, &high) > 0.99);
+        assert!(mann_whitney_u_greater(&high, &low) < 0.05);
+        assert!(mann_whitney_u_greater(&low, &high) > 0.95);
+        // overlapping noisy samples are not significantly different
+        let noisy = [9.0, 12.0, 10.5, 11.0, 9.8];
+        assert!(welch_t_test_greater(&noisy, &low) > 0.05);
+        assert!(mann_whitney_u_greater(&noisy, &low) > 0.05);
+    }
+
+    #[test]
+    fn mann_whitney_u_minimum() {
+        // 2 samples per size can never be significant at 0.05
+        assert!(mann_whitney_u_min_p_value(2) > 0.05);
+        assert!(mann_whitney_u_min_p_value(3) < 0.05);
+        assert!(mann_whitney_u_min_p_value(5) < 0.01);
+        assert_close(
+            mann_whitney_u_min_p_value(3),
+            mann_whitney_u_greater(&[4.0, 5.0, 6.0], &[1.0, 2.0, 3.0]),
+            1e-12,
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::StateError;
+use crate::parameters::{ComparisonMethod, ScalingParameters};
+use crate::performance::{PerformanceTable, SizeStatistics};
+
+/// observed scale metric of one pool size, see SizeStatistics
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SizeEntry {
+    pub size: usize,
+    pub mean: f64,
+    pub weight: f64,
+    pub observations: usize,
+}
+
+/// parameters the learned state depends on, a state is stale if they changed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StateParameters {
+    pub syscall_nrs: Vec<i32>,
+    pub check_interval_ms: u64,
+    pub comparison: String,
+    /// see ScalingParameters::scale_metric, named metrics as "named:<name>"
+    pub scale_metric: Option<String>,
+}
+
+impl StateParameters {
+    fn from_parameters(params: &ScalingParameters) -> Self {
+        let comparison = match params.comparison {
+            ComparisonMethod::StabilityFactor => "factor",
+            ComparisonMethod::WelchTTest => "welch",
+            ComparisonMethod::MannWhitneyU => "mannwhitney",
+        };
+        let scale_metric = match &params.named_metrics {
+            Some(named_metrics) => Some(format!("named:{}", named_metrics.scale_metric)),
+            None => params.scale_metric.clone(),
+        };
+        StateParameters {
+            syscall_nrs: params.syscall_nrs.clone(),
+            check_interval_ms: params.check_interval_ms,
+            comparison: comparison.to_string(),
+            scale_metric,
+        }
+    }
+}
+
+/// what an adapter learned during a run, saved at the end of a job and restored at the start
+/// of the next one, so repeated jobs with the same workload start at their best pool size
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LearnedState {
+    /// wall clock time of the export in seconds since the unix epoch
+    pub saved_at: u64,
+    /// best known pool size, None if nothing was learned yet
+    pub best_size: Option<usize>,
+    /// pool size at the time of the export
+    pub last_size: usize,
+    pub parameters: StateParameters,
+    pub performance: Vec<SizeEntry>,
+}
+
+impl LearnedState {
+    pub(crate) fn new(
+        saved_at: SystemTime,
+        last_size: usize,
+        params: &ScalingParameters,
+        performance_table: &PerformanceTable,
+    ) -> Self {
+        LearnedState {
+            saved_at: seconds_since_epoch(saved_at),
+            best_size: performance_table.best().map(|(size, _)| size),
+            last_size,
+            parameters: StateParameters::from_parameters(params),
+            performance: performance_table
+                .entries()
+                .map(|(size, statistics)| SizeEntry {
+                    size,
+                    mean: statistics.mean,
+                    weight: statistics.weight,
+                    observations: statistics.observations,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_toml_str(state: &str) -> Result<Self, StateError> {
+        toml::from_str(state).map_err(|e| StateError::Parse(e.to_string()))
+    }
+
+    pub fn to_toml_string(&self) -> Result<String, StateError> {
+        toml::to_string(self).map_err(|e| StateError::Parse(e.to_string()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, StateError> {
+        let state = fs::read_to_string(path).map_err(|e| StateError::Io(e.to_string()))?;
+        LearnedState::from_toml_str(&state)
+    }
+
+    /// written to a temporary file first, so a crash never leaves a truncated state behind
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), StateError> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, self.to_toml_string()?)
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .map_err(|e| StateError::Io(e.to_string()))
+    }
+
+    /// reject states that are older than max_age or were learned with other parameters
+    pub fn check(
+        &self,
+        params: &ScalingParameters,
+        now: SystemTime,
+        max_age: Duration,
+    ) -> Result<(), StateError> {
+        let age = seconds_since_epoch(now).saturating_sub(self.saved_at);
+        if age > max_age.as_secs() {
+            return Err(StateError::Stale(format!(
+                "saved {}s ago, max age {}s",
+                age,
+                max_age.as_secs()
+            )));
+        }
+        // states of custom metric functions cannot be told apart
+        if self.parameters.scale_metric.is_none() {
+            return Err(StateError::Stale(String::from("unknown scale metric")));
+        }
+        if self.parameters != StateParameters::from_parameters(params) {
+            return Err(StateError::Stale(String::from(
+                "learned with different parameters",
+            )));
+        }
+        Ok(())
+    }
+
+    /// pool size to start with, within the bounds of the parameters
+    pub fn initial_size(&self, params: &ScalingParameters) -> usize {
+        self.best_size
+            .unwrap_or(self.last_size)
+            .max(params.min_size)
+            .min(params.max_size)
+    }
+
+    /// restored entries count as at most one observation, so new observations quickly dominate
+    pub(crate) fn restore_into(&self, performance_table: &mut PerformanceTable) {
+        for entry in self.performance.iter() {
+            performance_table.insert_prior(
+                entry.size,
+                SizeStatistics {
+                    mean: entry.mean,
+                    weight: entry.weight.min(1.0),
+                    observations: entry.observations,
+                },
+            );
+        }
+    }
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_check_state() {
+        let params = ScalingParameters::default();
+        let mut performance_table = PerformanceTable::new(1.0);
+        performance_table.add(2, 10.0);
+        performance_table.add(4, 30.0);
+        performance_table.add(4, 20.0);
+        let now = SystemTime::now();
+        let state = LearnedState::new(now, 4, &params, &performance_table);
+        assert_eq!(state.best_size, Some(4));
+
+        let path = std::env::temp_dir().join(format!("adapter-state-{}.toml", std::process::id()));
+        state.save(&path).unwrap();
+        let loaded = LearnedState::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, state);
+
+        let max_age = Duration::from_secs(3600);
+        assert!(loaded.check(&params, now, max_age).is_ok());
+        assert!(matches!(
+            loaded.check(&params, now + Duration::from_secs(7200), max_age),
+            Err(StateError::Stale(_))
+        ));
+        let other_params = ScalingParameters::default().with_check_interval_ms(500);
+        assert!(matches!(
+            loaded.check(&other_params, now, max_age),
+            Err(StateError::Stale(_))
+        ));
+        let other_metric = ScalingParameters::default()
+            .with_catalog_metric("write_throughput")
+            .unwrap();
+        assert!(matches!(
+            loaded.check(&other_metric, now, max_age),
+            Err(StateError::Stale(_))
+        ));
+        let custom_params = ScalingParameters::new(
+            params.syscall_nrs.clone(),
+            Box::new(crate::metrics::read_write_bytes_per_ms),
+        );
+        let custom_state = LearnedState::new(now, 4, &custom_params, &performance_table);
+        assert!(matches!(
+            custom_state.check(&custom_params, now, max_age),
+            Err(StateError::Stale(_))
+        ));
+        assert_eq!(
+            loaded.initial_size(&ScalingParameters::default().with_bounds(1, 3)),
+            3
+        );
+
+        let mut restored = PerformanceTable::new(1.0);
+        loaded.restore_into(&mut restored);
+        assert_eq!(restored.best().map(|(size, _)| size), Some(4));
+        assert!((restored.get(4).unwrap().weight - 1.0).abs() < 1e-9);
+        assert_eq!(restored.get(4).unwrap().observations, 2);
+    }
+}
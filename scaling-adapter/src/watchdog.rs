@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use tracesets::TracesetSnapshot;
+
+use crate::intervals::IntervalData;
+
+/// implausible traceset data, scaling is frozen while the intervals show it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HealthIssue {
+    /// all counters stayed 0 although targets are registered and the pool is busy
+    ZeroCounters,
+    /// cumulative counters decreased although the targets did not change
+    DecreasingCounters,
+    /// interval took much longer than check_interval_ms
+    LongInterval(Duration),
+}
+
+impl HealthIssue {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HealthIssue::ZeroCounters => "zero_counters",
+            HealthIssue::DecreasingCounters => "decreasing_counters",
+            HealthIssue::LongInterval(_) => "long_interval",
+        }
+    }
+}
+
+/// health checks of one interval, None if the interval is plausible
+/// busy: jobs were queued or completed, so the targets should have done some io
+/// check_counters: false if the source has no kernel data (degraded mode)
+pub(crate) fn check_interval(
+    earlier: &TracesetSnapshot,
+    later: &TracesetSnapshot,
+    data: &IntervalData,
+    busy: bool,
+    check_counters: bool,
+    max_duration: Duration,
+) -> Option<HealthIssue> {
+    if data.duration > max_duration {
+        return Some(HealthIssue::LongInterval(data.duration));
+    }
+    if !check_counters {
+        return None;
+    }
+    if earlier.targets == later.targets && is_decreasing(earlier, later) {
+        return Some(HealthIssue::DecreasingCounters);
+    }
+    let is_zero = data.read_bytes == 0
+        && data.write_bytes == 0
+        && data.blkio_delay == 0
+        && data.syscalls_data.iter().all(|syscall| syscall.count == 0);
+    if is_zero && busy && data.amount_targets > 0 {
+        return Some(HealthIssue::ZeroCounters);
+    }
+    None
+}
+
+fn is_decreasing(earlier: &TracesetSnapshot, later: &TracesetSnapshot) -> bool {
+    let syscall_decreasing = earlier.syscalls_data.iter().any(|(syscall, earlier_data)| {
+        matches!(later.syscalls_data.get(syscall), Some(later_data)
+            if later_data.count < earlier_data.count
+                || later_data.total_time < earlier_data.total_time)
+    });
+    later.read_bytes < earlier.read_bytes
+        || later.write_bytes < earlier.write_bytes
+        || later.blkio_delay < earlier.blkio_delay
+        || syscall_decreasing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+    use std::time::SystemTime;
+
+    fn snapshot(write_bytes: u64, targets: &[i32]) -> TracesetSnapshot {
+        TracesetSnapshot {
+            read_bytes: 0,
+            write_bytes,
+            blkio_delay: 0,
+            syscalls_data: HashMap::new(),
+            targets: targets.iter().cloned().collect::<HashSet<i32>>(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn health_checks() {
+        let interval = Duration::from_millis(1000);
+        let max_duration = interval * 5;
+        let check = |earlier: &TracesetSnapshot, later: &TracesetSnapshot, busy, duration| {
+            let data = IntervalData::new_mixed(earlier, later, duration, duration, &[]);
+            check_interval(earlier, later, &data, busy, true, max_duration)
+        };
+        let earlier = snapshot(100, &[1]);
+        assert_eq!(check(&earlier, &snapshot(200, &[1]), true, interval), None);
+        // idle pools may not do any io
+        assert_eq!(check(&earlier, &snapshot(100, &[1]), false, interval), None);
+        assert_eq!(
+            check(&earlier, &snapshot(100, &[1]), true, interval),
+            Some(HealthIssue::ZeroCounters)
+        );
+        assert_eq!(
+            check(&earlier, &snapshot(50, &[1]), false, interval),
+            Some(HealthIssue::DecreasingCounters)
+        );
+        // counters of leaving targets may be lost
+        assert_eq!(check(&earlier, &snapshot(50, &[2]), false, interval), None);
+        assert_eq!(
+            check(&earlier, &snapshot(200, &[1]), true, interval * 10),
+            Some(HealthIssue::LongInterval(interval * 10))
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub use simulation::{Simulation, SimulationConfig, SimulationReport};
+pub use traceset::{SyntheticData, SyntheticTraceset};
+pub use workload::{Interference, Phase, Workload};
+
+mod simulation;
+mod traceset;
+mod workload;
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use clap::{App, Arg};
+use scaling_adapter::ScalingParameters;
+use simulator::{Simulation, SimulationConfig, Workload};
+
+fn main() {
+    env_logger::init();
+    let matches = App::new("simulator")
+        .version("1.0")
+        .about("evaluates scaling adapter parameters on a simulated workload")
+        .arg(
+            Arg::new("algo_params")
+                .required(true)
+                .multiple(true)
+                .value_name("ALGO_PARAMS")
+                .about("comma separated algorithm parameters, e.g. 1000,0.9 (one run per value)"),
+        )
+        .arg(
+            Arg::new("workload")
+                .long("workload")
+                .default_value("phases")
+                .value_name("WORKLOAD")
+                .about("simulated workload: single, phases or interference"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .default_value("0")
+                .value_name("SEED")
+                .about("seed of the measurement noise"),
+        )
+        .arg(
+            Arg::new("tick_ms")
+                .long("tick-ms")
+                .default_value("100")
+                .value_name("TICK_MS")
+                .about("simulated ms between two scaling advice requests"),
+        )
+        .get_matches();
+
+    let workload_name = matches.value_of("workload").unwrap();
+    let workload = Workload::preset(workload_name).expect("unknown workload");
+    let seed: u64 = matches
+        .value_of("seed")
+        .unwrap()
+        .parse()
+        .expect("invalid seed");
+    let tick_ms: u64 = matches
+        .value_of("tick_ms")
+        .unwrap()
+        .parse()
+        .expect("invalid tick ms");
+    let config = SimulationConfig {
+        tick: Duration::from_millis(tick_ms),
+        seed,
+        ..SimulationConfig::default()
+    };
+    println!(
+        "workload: {}, simulated duration: {}s",
+        workload_name,
+        workload.duration().as_secs()
+    );
+    let simulation = Simulation::new(workload, config);
+    for algo_params in matches.values_of("algo_params").unwrap() {
+        let params = ScalingParameters::default()
+            .try_with_algo_params(algo_params)
+            .expect("invalid algo params");
+        let report = simulation.run(params);
+        let convergence_times: Vec<String> = report
+            .convergence_times
+            .iter()
+            .map(|time| match time {
+                Some(time) => format!("{:.1}s", time.as_secs_f64()),
+                None => "-".to_string(),
+            })
+            .collect();
+        println!(
+            "{}: convergence: [{}], at optimum: {:.1}%, work: {:.1}% of optimal, size changes: {}, final size: {}",
+            algo_params,
+            convergence_times.join(", "),
+            100.0 * report.fraction_at_optimum(),
+            100.0 * report.work_efficiency(),
+            report.size_changes,
+            report.final_size
+        );
+    }
+}
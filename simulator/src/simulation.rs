@@ -0,0 +1,180 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use log::debug;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use scaling_adapter::{ManualClock, ScalingAdapter, ScalingParameters};
+
+use crate::{
+    traceset::{SyntheticData, SyntheticTraceset},
+    workload::Workload,
+};
+
+pub struct SimulationConfig {
+    /// simulated time between two calls of get_scaling_advice
+    pub tick: Duration,
+    pub initial_size: usize,
+    /// largest pool size considered when looking for the optimum
+    pub max_size: usize,
+    /// a pool size is at the optimum if its expected throughput reaches
+    /// this fraction of the optimal throughput
+    pub optimum_tolerance: f64,
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            tick: Duration::from_millis(100),
+            initial_size: 1,
+            max_size: 64,
+            optimum_tolerance: 0.95,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SimulationReport {
+    /// per phase: time from the phase start until the pool first reached the optimum
+    pub convergence_times: Vec<Option<Duration>>,
+    pub time_at_optimum: Duration,
+    pub total_time: Duration,
+    /// bytes processed by the pool
+    pub total_work: f64,
+    /// bytes an oracle that always runs at the optimal pool size would have processed (no noise)
+    pub optimal_work: f64,
+    pub size_changes: usize,
+    pub final_size: usize,
+}
+
+impl SimulationReport {
+    pub fn fraction_at_optimum(&self) -> f64 {
+        self.time_at_optimum.as_secs_f64() / self.total_time.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn work_efficiency(&self) -> f64 {
+        self.total_work / self.optimal_work.max(f64::EPSILON)
+    }
+}
+
+/// drives a scaling adapter with a synthetic traceset and a manual clock
+/// the pool size follows the advice of the adapter, the workload determines how much work
+/// the pool does per tick
+pub struct Simulation {
+    workload: Workload,
+    config: SimulationConfig,
+}
+
+impl Simulation {
+    pub fn new(workload: Workload, config: SimulationConfig) -> Self {
+        Simulation { workload, config }
+    }
+
+    /// run the whole workload, the clock of the parameters is replaced by the simulated clock
+    pub fn run(&self, params: ScalingParameters) -> SimulationReport {
+        let clock = Arc::new(ManualClock::new());
+        let data = Arc::new(Mutex::new(SyntheticData::default()));
+        let source = SyntheticTraceset::new(data.clone(), clock.clone());
+        let mut adapter =
+            ScalingAdapter::with_source(params.with_clock(clock.clone()), Box::new(source));
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        let mut targets: Vec<i32> = Vec::new();
+        let mut next_target = 1;
+        for _ in 0..self.config.initial_size.max(1) {
+            adapter.add_tracee(next_target);
+            targets.push(next_target);
+            next_target += 1;
+        }
+        let tick_ms = self.config.tick.as_secs_f64() * 1000.0;
+        let total_time = self.workload.duration();
+        let mut report = SimulationReport {
+            convergence_times: vec![None; self.workload.phases.len()],
+            time_at_optimum: Duration::from_secs(0),
+            total_time,
+            total_work: 0.0,
+            optimal_work: 0.0,
+            size_changes: 0,
+            final_size: targets.len(),
+        };
+        let mut phase_start = Duration::from_secs(0);
+        let mut current_phase = 0;
+        let mut time = Duration::from_secs(0);
+        while time < total_time {
+            let phase = self.workload.phase_index(time);
+            if phase != current_phase {
+                current_phase = phase;
+                phase_start = time;
+            }
+            let size = targets.len();
+            let expected = self.workload.expected_throughput(size, time);
+            let (_optimal_size, optimal) = self.workload.optimum(time, self.config.max_size);
+            // noisy measurement, gaussian noise via Box-Muller transform
+            let u1: f64 = rng.gen_range(f64::EPSILON, 1.0);
+            let u2: f64 = rng.gen();
+            let normal = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+            let throughput = (expected * (1.0 + self.workload.noise * normal)).max(0.0);
+            let work = throughput * tick_ms;
+            data.lock().unwrap().write_bytes += work as u64;
+            report.total_work += work;
+            report.optimal_work += optimal * tick_ms;
+            if expected >= self.config.optimum_tolerance * optimal {
+                report.time_at_optimum += self.config.tick;
+                let convergence_time = &mut report.convergence_times[phase];
+                if convergence_time.is_none() {
+                    *convergence_time = Some(time - phase_start);
+                }
+            }
+            clock.advance(self.config.tick);
+            time += self.config.tick;
+            let advice = adapter.get_scaling_advice(0);
+            if advice > 0 {
+                for _ in 0..advice {
+                    adapter.add_tracee(next_target);
+                    targets.push(next_target);
+                    next_target += 1;
+                }
+            } else if advice < 0 {
+                for _ in 0..(-advice).min(targets.len() as i32 - 1) {
+                    let target = targets.pop().unwrap();
+                    adapter.remove_tracee(target);
+                }
+            }
+            if advice != 0 {
+                report.size_changes += 1;
+                debug!("SIM: {:?}: pool size {}", time, targets.len());
+            }
+        }
+        report.final_size = targets.len();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workload::Phase;
+
+    #[test]
+    fn simulation_reaches_optimum() {
+        // steep curve with optimum at 4 threads
+        let workload = Workload {
+            phases: vec![Phase {
+                duration: Duration::from_secs(120),
+                lambda: 1000.0,
+                sigma: 0.0,
+                kappa: 0.06,
+            }],
+            interference: Vec::new(),
+            noise: 0.0,
+        };
+        let simulation = Simulation::new(workload, SimulationConfig::default());
+        let report = simulation.run(ScalingParameters::default());
+        assert!(report.convergence_times[0].unwrap() < Duration::from_secs(30));
+        assert!(report.time_at_optimum <= report.total_time);
+        assert!(report.fraction_at_optimum() > 0.5);
+        assert!(report.work_efficiency() > 0.8 && report.work_efficiency() <= 1.0);
+    }
+}
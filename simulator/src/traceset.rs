@@ -0,0 +1,54 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use scaling_adapter::{tracesets::TracesetSnapshot, Clock, ManualClock, TracesetSource};
+
+/// cumulative data of the simulated targets
+#[derive(Default)]
+pub struct SyntheticData {
+    pub write_bytes: u64,
+    pub blkio_delay: u64,
+    pub targets: HashSet<i32>,
+}
+
+/// traceset source whose data is written by the simulation instead of the kernel
+pub struct SyntheticTraceset {
+    data: Arc<Mutex<SyntheticData>>,
+    clock: Arc<ManualClock>,
+}
+
+impl SyntheticTraceset {
+    pub fn new(data: Arc<Mutex<SyntheticData>>, clock: Arc<ManualClock>) -> Self {
+        SyntheticTraceset { data, clock }
+    }
+}
+
+impl TracesetSource for SyntheticTraceset {
+    fn get_snapshot(&self) -> TracesetSnapshot {
+        let data = self.data.lock().unwrap();
+        TracesetSnapshot {
+            read_bytes: 0,
+            write_bytes: data.write_bytes,
+            blkio_delay: data.blkio_delay,
+            syscalls_data: HashMap::new(),
+            targets: data.targets.clone(),
+            timestamp: self.clock.wall_time(),
+        }
+    }
+
+    fn register_target(&mut self, target: i32) -> bool {
+        self.data.lock().unwrap().targets.insert(target);
+        true
+    }
+
+    fn deregister_target(&mut self, target: i32) -> bool {
+        self.data.lock().unwrap().targets.remove(&target);
+        true
+    }
+
+    fn get_amount_targets(&self) -> usize {
+        self.data.lock().unwrap().targets.len()
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+/// part of the workload with its own throughput curve
+/// throughput follows the Universal Scalability Law:
+/// X(N) = lambda * N / (1 + sigma * (N - 1) + kappa * N * (N - 1))
+#[derive(Clone, Debug)]
+pub struct Phase {
+    pub duration: Duration,
+    /// throughput of a single thread in bytes per ms
+    pub lambda: f64,
+    /// contention coefficient
+    pub sigma: f64,
+    /// coherency coefficient
+    pub kappa: f64,
+}
+
+/// external load on the shared resource (e.g. another tenant on the same disk)
+#[derive(Clone, Debug)]
+pub struct Interference {
+    pub start: Duration,
+    pub end: Duration,
+    /// throughput is multiplied with this factor
+    pub throughput_factor: f64,
+    /// added to the contention coefficient of the active phase
+    pub extra_contention: f64,
+}
+
+/// throughput versus concurrency model of a workload
+#[derive(Clone, Debug)]
+pub struct Workload {
+    pub phases: Vec<Phase>,
+    pub interference: Vec<Interference>,
+    /// relative standard deviation of the measured throughput
+    pub noise: f64,
+}
+
+impl Workload {
+    /// available presets: "single", "phases", "interference"
+    pub fn preset(name: &str) -> Option<Workload> {
+        let io_bound = Phase {
+            duration: Duration::from_secs(300),
+            lambda: 1000.0,
+            sigma: 0.05,
+            kappa: 0.002,
+        };
+        let contended = Phase {
+            duration: Duration::from_secs(300),
+            lambda: 1500.0,
+            sigma: 0.1,
+            kappa: 0.02,
+        };
+        match name {
+            "single" => Some(Workload {
+                phases: vec![io_bound],
+                interference: Vec::new(),
+                noise: 0.05,
+            }),
+            "phases" => Some(Workload {
+                phases: vec![io_bound.clone(), contended, io_bound],
+                interference: Vec::new(),
+                noise: 0.05,
+            }),
+            "interference" => Some(Workload {
+                phases: vec![Phase {
+                    duration: Duration::from_secs(600),
+                    ..io_bound
+                }],
+                interference: vec![Interference {
+                    start: Duration::from_secs(200),
+                    end: Duration::from_secs(400),
+                    throughput_factor: 0.7,
+                    extra_contention: 0.2,
+                }],
+                noise: 0.05,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.duration).sum()
+    }
+
+    /// index of the phase active at time (since start), the last phase never ends
+    pub fn phase_index(&self, time: Duration) -> usize {
+        let mut phase_end = Duration::from_secs(0);
+        for (index, phase) in self.phases.iter().enumerate() {
+            phase_end += phase.duration;
+            if time < phase_end {
+                return index;
+            }
+        }
+        self.phases.len().saturating_sub(1)
+    }
+
+    /// throughput without noise in bytes per ms
+    pub fn expected_throughput(&self, size: usize, time: Duration) -> f64 {
+        let phase = match self.phases.get(self.phase_index(time)) {
+            Some(phase) => phase,
+            None => return 0.0,
+        };
+        let mut sigma = phase.sigma;
+        let mut factor = 1.0;
+        for interference in self
+            .interference
+            .iter()
+            .filter(|interference| interference.start <= time && time < interference.end)
+        {
+            sigma += interference.extra_contention;
+            factor *= interference.throughput_factor;
+        }
+        let n = size as f64;
+        factor * phase.lambda * n / (1.0 + sigma * (n - 1.0) + phase.kappa * n * (n - 1.0))
+    }
+
+    /// pool size with the highest expected throughput and that throughput
+    pub fn optimum(&self, time: Duration, max_size: usize) -> (usize, f64) {
+        (1..=max_size.max(1))
+            .map(|size| (size, self.expected_throughput(size, time)))
+            .fold((1, 0.0), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workload_model() {
+        let workload = Workload::preset("phases").unwrap();
+        assert_eq!(workload.duration(), Duration::from_secs(900));
+        assert_eq!(workload.phase_index(Duration::from_secs(100)), 0);
+        assert_eq!(workload.phase_index(Duration::from_secs(400)), 1);
+        assert_eq!(workload.phase_index(Duration::from_secs(1000)), 2);
+        // peak at sqrt((1 - sigma) / kappa)
+        assert_eq!(workload.optimum(Duration::from_secs(0), 64).0, 22);
+        assert_eq!(workload.optimum(Duration::from_secs(400), 64).0, 7);
+        let interference = Workload::preset("interference").unwrap();
+        let quiet = interference.expected_throughput(8, Duration::from_secs(100));
+        let loaded = interference.expected_throughput(8, Duration::from_secs(300));
+        assert!(loaded < 0.7 * quiet);
+        assert!(interference.optimum(Duration::from_secs(300), 64).0 < 22);
+    }
+}
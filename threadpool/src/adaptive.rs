@@ -2,13 +2,15 @@
 #![allow(dead_code)]
 use std::{
     collections::{HashSet, VecDeque},
-    sync::{atomic, atomic::AtomicBool, atomic::AtomicUsize, Arc, Condvar, Mutex},
+    sync::{
+        atomic, atomic::AtomicBool, atomic::AtomicI32, atomic::AtomicUsize, Arc, Condvar, Mutex,
+    },
     thread,
     time::Duration,
 };
 
 use log::debug;
-use scaling_adapter::ScalingAdapter;
+use scaling_adapter::{Advisor, AdvisorHandle, JobCounter, ScalingAdapter};
 
 use crate::{get_pid, Job, Threadpool};
 
@@ -29,6 +31,32 @@ enum WorkItem {
     ScaleCommand(ScaleCommand),
 }
 
+enum Scaler {
+    // workers advance the intervals of the adapter themselves
+    Inline(Box<Mutex<ScalingAdapter>>),
+    // adapter runs on its own thread, workers only read the target size
+    Advisor {
+        advisor: Mutex<Advisor>,
+        handle: AdvisorHandle,
+    },
+}
+
+impl Scaler {
+    fn add_tracee(&self, tracee_pid: i32) -> bool {
+        match self {
+            Scaler::Inline(adapter) => adapter.lock().unwrap().add_tracee(tracee_pid),
+            Scaler::Advisor { handle, .. } => handle.add_tracee(tracee_pid),
+        }
+    }
+
+    fn remove_tracee(&self, tracee_pid: i32) -> bool {
+        match self {
+            Scaler::Inline(adapter) => adapter.lock().unwrap().remove_tracee(tracee_pid),
+            Scaler::Advisor { handle, .. } => handle.remove_tracee(tracee_pid),
+        }
+    }
+}
+
 pub struct AdaptiveThreadpool {
     work_queue: Mutex<VecDeque<WorkItem>>,
     workers: Mutex<HashSet<i32>>,
@@ -40,7 +68,11 @@ pub struct AdaptiveThreadpool {
     // used to signal blocked (on empty work_queue) workers
     work_queue_non_empty: Condvar,
     is_stopping: AtomicBool,
-    scaling_adapter: Mutex<ScalingAdapter>,
+    scaler: Scaler,
+    // workers about to be spawned minus workers about to terminate (queued scale commands)
+    pending_workers: AtomicI32,
+    // completed jobs, the adapter scales by them in degraded mode
+    completed_jobs: JobCounter,
     next_worker_id: AtomicUsize,
 }
 
@@ -77,6 +109,10 @@ impl Threadpool for AdaptiveThreadpool {
             debug!("some workers still active, wait on exit condition variable");
             workers = self.all_workers_exited.wait(workers).unwrap();
         }
+        drop(workers);
+        if let Scaler::Advisor { advisor, .. } = &self.scaler {
+            advisor.lock().unwrap().stop();
+        }
     }
 }
 
@@ -86,12 +122,12 @@ fn worker_loop(threadpool: Arc<AdaptiveThreadpool>) {
     {
         let mut workers = threadpool.workers.lock().unwrap();
         workers.insert(worker_pid);
+        threadpool
+            .pending_workers
+            .fetch_sub(1, atomic::Ordering::Relaxed);
     }
-    {
-        let mut adapter = threadpool.scaling_adapter.lock().unwrap();
-        if !adapter.add_tracee(worker_pid) {
-            panic!("worker {} could not add itself as tracee", worker_pid);
-        }
+    if !threadpool.scaler.add_tracee(worker_pid) {
+        panic!("worker {} could not add itself as tracee", worker_pid);
     }
     loop {
         threadpool.adapt_size();
@@ -125,6 +161,7 @@ fn worker_loop(threadpool: Arc<AdaptiveThreadpool>) {
                 *busy_count += 1;
                 drop(busy_count);
                 job.execute();
+                threadpool.completed_jobs.job_completed();
                 let mut busy_count = threadpool.busy_workers_count.lock().unwrap();
                 *busy_count -= 1;
                 let all_workers_idle = *busy_count == 0;
@@ -146,6 +183,9 @@ fn worker_loop(threadpool: Arc<AdaptiveThreadpool>) {
             WorkItem::ScaleCommand(ScaleCommand::Terminate) => {
                 let mut workers = threadpool.workers.lock().unwrap();
                 let amount_workers = workers.len();
+                threadpool
+                    .pending_workers
+                    .fetch_add(1, atomic::Ordering::Relaxed);
                 // only terminate self if not the last worker
                 if amount_workers > 1 {
                     debug!("terminate command: worker {}", worker_pid);
@@ -156,7 +196,7 @@ fn worker_loop(threadpool: Arc<AdaptiveThreadpool>) {
         }
     }
     debug!("worker terminating, pid: {}", worker_pid);
-    threadpool.scaling_adapter.lock().unwrap().remove_tracee(worker_pid);
+    threadpool.scaler.remove_tracee(worker_pid);
     if threadpool.workers.lock().unwrap().len() == 0 {
         threadpool.all_workers_exited.notify_all();
     }
@@ -164,6 +204,29 @@ fn worker_loop(threadpool: Arc<AdaptiveThreadpool>) {
 
 impl AdaptiveThreadpool {
     pub fn new(scaling_adapter: ScalingAdapter) -> Arc<Self> {
+        let completed_jobs = scaling_adapter.job_counter();
+        Self::with_scaler(
+            Scaler::Inline(Box::new(Mutex::new(scaling_adapter))),
+            completed_jobs,
+        )
+    }
+
+    /// the adapter runs on a background advisor thread at a fixed interval,
+    /// workers adapt the pool size to the published target size without locking the adapter
+    pub fn new_with_advisor(scaling_adapter: ScalingAdapter) -> Arc<Self> {
+        let completed_jobs = scaling_adapter.job_counter();
+        let advisor = Advisor::start(scaling_adapter, 1);
+        let handle = advisor.handle();
+        Self::with_scaler(
+            Scaler::Advisor {
+                advisor: Mutex::new(advisor),
+                handle,
+            },
+            completed_jobs,
+        )
+    }
+
+    fn with_scaler(scaler: Scaler, completed_jobs: JobCounter) -> Arc<Self> {
         let thread_pool = Arc::new(AdaptiveThreadpool {
             work_queue: Mutex::new(VecDeque::new()),
             workers: Mutex::new(HashSet::new()),
@@ -172,7 +235,10 @@ impl AdaptiveThreadpool {
             all_workers_exited: Condvar::new(),
             work_queue_non_empty: Condvar::new(),
             is_stopping: AtomicBool::new(false),
-            scaling_adapter: Mutex::new(scaling_adapter),
+            scaler,
+            // the first worker
+            pending_workers: AtomicI32::new(1),
+            completed_jobs,
             next_worker_id: AtomicUsize::new(0),
         });
         thread_pool.clone().spawn_worker();
@@ -181,13 +247,38 @@ impl AdaptiveThreadpool {
 
     fn adapt_size(&self) {
         let queue_size = self.work_queue.lock().unwrap().len() as i32;
-        let mut to_scale = self.scaling_adapter.lock().unwrap().get_scaling_advice(queue_size);
-        debug!("got scaling advice: {}", to_scale);
-        let current_size = self.workers.lock().unwrap().len() as i32;
-        //
-        if current_size + to_scale < 1 {
-            to_scale = -(current_size - 1);
+        match &self.scaler {
+            Scaler::Inline(adapter) => {
+                let mut adapter = adapter.lock().unwrap();
+                if let Some(target_size) = adapter.get_target_size(queue_size) {
+                    self.scale_to(target_size);
+                }
+            }
+            Scaler::Advisor { advisor, handle } => {
+                // only one worker at a time compares target and planned size, others skip
+                let _guard = match advisor.try_lock() {
+                    Ok(guard) => guard,
+                    Err(_) => return,
+                };
+                handle.report_queue_size(queue_size);
+                self.scale_to(handle.target_size());
+            }
         }
+    }
+
+    // caller must make sure no other worker is scaling at the same time
+    fn scale_to(&self, target_size: usize) {
+        // workers plus queued scale commands, lost or ignored commands are corrected next time
+        let planned_size = self.workers.lock().unwrap().len() as i32
+            + self.pending_workers.load(atomic::Ordering::Relaxed);
+        let to_scale = target_size.max(1) as i32 - planned_size;
+        if to_scale != 0 {
+            debug!("target size differs from planned size by {}", to_scale);
+        }
+        self.push_scale_commands(to_scale);
+    }
+
+    fn push_scale_commands(&self, to_scale: i32) {
         let (scale_command, n) = match to_scale.cmp(&0) {
             std::cmp::Ordering::Greater => (ScaleCommand::Clone, to_scale),
             std::cmp::Ordering::Less => (ScaleCommand::Terminate, -to_scale),
@@ -201,6 +292,8 @@ impl AdaptiveThreadpool {
             for _ in 0..n {
                 work_queue.push_front(WorkItem::ScaleCommand(scale_command));
             }
+            self.pending_workers
+                .fetch_add(to_scale, atomic::Ordering::Relaxed);
         }
     }
 
@@ -222,19 +315,17 @@ impl AdaptiveThreadpool {
 
 #[cfg(test)]
 mod tests {
-    use scaling_adapter::{IntervalData, IntervalDerivedData, ScalingParameters};
+    use scaling_adapter::{metrics::written_bytes_per_ms, ScalingParameters};
 
     use super::*;
 
-    pub fn written_bytes_per_ms(interval_data: &IntervalData) -> IntervalDerivedData {
-        let duration_ms = interval_data.end_millis() - interval_data.start_millis();
-        // conversion to f64 precise for durations under 1000 years for sure
-        // conversion to f64 precise for under a petabyte of written bytes
-        let write_bytes_per_ms = (interval_data.write_bytes as f64) / (duration_ms as f64);
-        IntervalDerivedData {
-            scale_metric: write_bytes_per_ms,
-            reset_metric: write_bytes_per_ms,
-        }
+    #[test]
+    fn create_with_advisor() {
+        let adapter_params = ScalingParameters::new(vec![1, 2], Box::new(written_bytes_per_ms));
+        let adapter = ScalingAdapter::new(adapter_params).expect("adapter creation failed");
+        let pool = AdaptiveThreadpool::new_with_advisor(adapter);
+        pool.wait_completion();
+        pool.destroy();
     }
 
     #[test]
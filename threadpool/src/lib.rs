@@ -92,6 +92,15 @@ mod tests {
         print_jobs(adaptive_pool);
     }
 
+    #[test]
+    fn adaptive_degraded_print_jobs() {
+        setup();
+        let adapter = get_degraded_adapter();
+        assert!(adapter.is_degraded());
+        let adaptive_pool = AdaptiveThreadpool::new(adapter);
+        print_jobs(adaptive_pool);
+    }
+
     #[test]
     fn watermark_create_wait_destroy() {
         setup();
@@ -117,6 +126,11 @@ mod tests {
         ScalingAdapter::new(adapter_params).expect("adapter creation failed")
     }
 
+    /// works without kernel traceset support
+    fn get_degraded_adapter() -> ScalingAdapter {
+        ScalingAdapter::degraded(ScalingParameters::default())
+    }
+
     fn print_jobs(threadpool: Arc<dyn Threadpool>) {
         for i in 0..10 {
             let job_function = move || print_ix10(i);